@@ -0,0 +1,92 @@
+use std::{
+    env,
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
+
+// Minimal command line handling for the day binaries: an optional leading subcommand,
+// then `--name value`, `--name=value` and `--name`
+
+pub fn subcommand() -> Option<String> {
+    subcommand_in(&env::args().skip(1).collect::<Vec<String>>())
+}
+
+pub fn value<T: FromStr>(name: &str) -> Result<Option<T>> {
+    value_in(&env::args().skip(1).collect::<Vec<String>>(), name)
+}
+
+pub fn flag(name: &str) -> bool {
+    flag_in(&env::args().skip(1).collect::<Vec<String>>(), name)
+}
+
+fn subcommand_in(args: &[String]) -> Option<String> {
+    args.first().filter(|arg| !arg.starts_with("--")).cloned()
+}
+
+fn value_in<T: FromStr>(args: &[String], name: &str) -> Result<Option<T>> {
+    let flag = format!("--{}", name);
+    let raw = args.iter().enumerate().find_map(|(i, arg)| {
+        if *arg == flag {
+            Some(args.get(i + 1).map(String::as_str))
+        } else {
+            arg.strip_prefix(&flag)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(Some)
+        }
+    });
+    match raw {
+        None => Ok(None),
+        Some(Some(s)) => T::from_str(s).map(Some).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value {:?} for {}", s, flag),
+            )
+        }),
+        Some(None) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("missing value for {}", flag),
+        )),
+    }
+}
+
+fn flag_in(args: &[String], name: &str) -> bool {
+    let flag = format!("--{}", name);
+    args.contains(&flag)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn values() {
+        let args = args("--top 5 --width=40 --verbose --name");
+        assert_eq!(value_in::<usize>(&args, "top").unwrap(), Some(5));
+        assert_eq!(value_in::<usize>(&args, "width").unwrap(), Some(40));
+        assert_eq!(value_in::<usize>(&args, "height").unwrap(), None);
+        assert!(value_in::<usize>(&args, "verbose").is_err());
+        assert!(value_in::<String>(&args, "name").is_err());
+    }
+
+    #[test]
+    fn subcommands() {
+        assert_eq!(
+            subcommand_in(&args("optimise --budget 3")),
+            Some("optimise".to_owned())
+        );
+        assert_eq!(subcommand_in(&args("--budget 3")), None);
+        assert_eq!(subcommand_in(&[]), None);
+    }
+
+    #[test]
+    fn flags() {
+        let args = args("--top 5 --verbose");
+        assert!(flag_in(&args, "verbose"));
+        assert!(!flag_in(&args, "trace"));
+    }
+}
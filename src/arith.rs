@@ -0,0 +1,75 @@
+use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub};
+use std::{error::Error, fmt, io};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Overflow(pub &'static str);
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "attempt to {} with overflow", self.0)
+    }
+}
+
+impl Error for Overflow {}
+
+impl From<Overflow> for io::Error {
+    fn from(e: Overflow) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+pub type Checked<T> = std::result::Result<T, Overflow>;
+
+// Without the `checked-arithmetic` feature these behave exactly like the plain operators
+#[cfg(feature = "checked-arithmetic")]
+macro_rules! checked_op {
+    ($a:expr, $b:expr, $checked:ident, $op:tt, $name:literal) => {
+        $a.$checked($b).ok_or(Overflow($name))
+    };
+}
+
+#[cfg(not(feature = "checked-arithmetic"))]
+macro_rules! checked_op {
+    ($a:expr, $b:expr, $checked:ident, $op:tt, $name:literal) => {
+        Ok($a.clone() $op $b.clone())
+    };
+}
+
+pub fn add<T: CheckedAdd + Clone>(a: &T, b: &T) -> Checked<T> {
+    checked_op!(a, b, checked_add, +, "add")
+}
+
+pub fn sub<T: CheckedSub + Clone>(a: &T, b: &T) -> Checked<T> {
+    checked_op!(a, b, checked_sub, -, "subtract")
+}
+
+pub fn mul<T: CheckedMul + Clone>(a: &T, b: &T) -> Checked<T> {
+    checked_op!(a, b, checked_mul, *, "multiply")
+}
+
+pub fn div<T: CheckedDiv + Clone>(a: &T, b: &T) -> Checked<T> {
+    checked_op!(a, b, checked_div, /, "divide")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn in_range() {
+        assert_eq!(add(&250u8, &5), Ok(255));
+        assert_eq!(sub(&5i32, &7), Ok(-2));
+        assert_eq!(mul(&16u8, &15), Ok(240));
+        assert_eq!(div(&-7i64, &2), Ok(-3));
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn out_of_range() {
+        assert_eq!(add(&250u8, &6), Err(Overflow("add")));
+        assert_eq!(sub(&0usize, &1), Err(Overflow("subtract")));
+        assert_eq!(mul(&i64::MAX, &2), Err(Overflow("multiply")));
+        assert_eq!(div(&1isize, &0), Err(Overflow("divide")));
+    }
+}
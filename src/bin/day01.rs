@@ -1,7 +1,13 @@
+use aoc_2022::args;
+use itertools::Itertools;
+use serde::Serialize;
 use std::str::FromStr;
 use std::{
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
     fs::File,
-    io::{BufRead, BufReader, ErrorKind, Result},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
 };
 
@@ -9,37 +15,258 @@ fn main() -> Result<()> {
     let path = Path::new("./data/day01.txt");
     let lines = BufReader::new(File::open(path)?).lines();
 
-    let totals = count_calories(lines)?;
+    if let Some(k) = args::value::<usize>("top")? {
+        let ranking = itertools::process_results(Elves::new(lines), |elves| top_k(elves, k))?;
+        for (rank, elf) in ranking.iter().enumerate() {
+            println!(
+                "#{} elf {} carries {} calories in {} items",
+                rank + 1,
+                elf.index + 1,
+                elf.total,
+                elf.items.len()
+            );
+        }
+        println!(
+            "top{}_calories = {}",
+            k,
+            ranking.iter().map(|elf| elf.total).sum::<usize>()
+        );
+        return Result::Ok(());
+    }
+
+    let elves = count_calories(lines)?;
 
-    println!("max_calories  = {}", max_calories(&totals));
-    println!("top3_calories = {}", top3_calories(&totals));
+    if args::flag("stats") {
+        let bucket_width = args::value::<usize>("bucket-width")?.unwrap_or(10000);
+        if bucket_width == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "--bucket-width must be positive",
+            ));
+        }
+        match statistics(&elves, bucket_width) {
+            Some(stats) if args::flag("json") => {
+                println!("{}", serde_json::to_string_pretty(&stats)?)
+            }
+            Some(stats) => print_statistics(&stats),
+            None => println!("no elves"),
+        }
+        return Result::Ok(());
+    }
+
+    println!("max_calories  = {}", max_calories(&elves));
+    println!("top3_calories = {}", top3_calories(&elves));
     Result::Ok(())
 }
 
-fn count_calories<I: Iterator<Item = Result<String>>>(input: I) -> Result<Vec<usize>> {
-    let mut reindeer_totals: Vec<usize> = Vec::new();
-    let mut total_this_reindeer: usize = 0;
-    for s in input {
-        let line = s?;
-        if line.is_empty() {
-            reindeer_totals.push(total_this_reindeer);
-            total_this_reindeer = 0;
-        } else {
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Elf {
+    index: usize,
+    total: usize,
+    items: Vec<usize>,
+}
+
+// Groups lines into elves as they are read; a blank line ends the current elf
+struct Elves<I> {
+    lines: I,
+    index: usize,
+}
+
+impl<I> Elves<I> {
+    fn new(lines: I) -> Self {
+        Elves { lines, index: 0 }
+    }
+}
+
+impl<I: Iterator<Item = Result<String>>> Elves<I> {
+    fn next_elf(&mut self) -> Result<Option<Elf>> {
+        let mut items = Vec::new();
+        let mut seen_line = false;
+        for s in self.lines.by_ref() {
+            seen_line = true;
+            let line = s?;
+            if line.is_empty() {
+                break;
+            }
             let calories: usize = usize::from_str(&line).map_err(|_| ErrorKind::InvalidData)?;
-            total_this_reindeer += calories;
+            items.push(calories);
+        }
+        if !seen_line {
+            return Result::Ok(None);
+        }
+        let elf = Elf {
+            index: self.index,
+            total: items.iter().sum(),
+            items,
+        };
+        self.index += 1;
+        Result::Ok(Some(elf))
+    }
+}
+
+impl<I: Iterator<Item = Result<String>>> Iterator for Elves<I> {
+    type Item = Result<Elf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_elf().transpose()
+    }
+}
+
+fn count_calories<I: Iterator<Item = Result<String>>>(input: I) -> Result<Vec<Elf>> {
+    Elves::new(input).collect()
+}
+
+// Orders elves by total, preferring the earlier elf on a tie
+struct Ranked<E>(E);
+
+impl<E: Borrow<Elf>> Ranked<E> {
+    fn key(&self) -> (usize, Reverse<usize>) {
+        let elf = self.0.borrow();
+        (elf.total, Reverse(elf.index))
+    }
+}
+
+impl<E: Borrow<Elf>> PartialEq for Ranked<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<E: Borrow<Elf>> Eq for Ranked<E> {}
+
+impl<E: Borrow<Elf>> Ord for Ranked<E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl<E: Borrow<Elf>> PartialOrd for Ranked<E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Keeps the k best elves seen so far in a min-heap, returning them best first
+fn top_k<E: Borrow<Elf>, I: IntoIterator<Item = E>>(elves: I, k: usize) -> Vec<E> {
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    for elf in elves {
+        heap.push(Reverse(Ranked(elf)));
+        if heap.len() > k {
+            heap.pop();
         }
     }
-    reindeer_totals.push(total_this_reindeer);
-    reindeer_totals.sort();
-    Result::Ok(reindeer_totals)
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(Ranked(elf))| elf)
+        .collect_vec()
+}
+
+fn top_calories(elves: &[Elf], k: usize) -> usize {
+    top_k(elves, k).into_iter().map(|elf| elf.total).sum()
+}
+
+fn max_calories(elves: &[Elf]) -> usize {
+    top_calories(elves, 1)
+}
+
+fn top3_calories(elves: &[Elf]) -> usize {
+    top_calories(elves, 3)
 }
 
-fn max_calories(totals: &Vec<usize>) -> usize {
-    totals[totals.len() - 1]
+#[derive(Debug, PartialEq, Serialize)]
+struct Statistics {
+    count: usize,
+    mean: f64,
+    median: f64,
+    percentiles: Vec<Percentile>,
+    histogram: Vec<Bucket>,
+    empty_packs: Vec<usize>,
 }
 
-fn top3_calories(totals: &Vec<usize>) -> usize {
-    totals[totals.len() - 3..].into_iter().sum()
+#[derive(Debug, PartialEq, Serialize)]
+struct Percentile {
+    percent: usize,
+    total: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Bucket {
+    from: usize,
+    to: usize,
+    count: usize,
+}
+
+const PERCENTS: [usize; 5] = [10, 25, 50, 75, 90];
+
+fn statistics(elves: &[Elf], bucket_width: usize) -> Option<Statistics> {
+    let totals = elves.iter().map(|elf| elf.total).sorted().collect_vec();
+    let count = totals.len();
+    let max = *totals.last()?;
+    let mean = totals.iter().sum::<usize>() as f64 / count as f64;
+    let median = if count % 2 == 0 {
+        (totals[count / 2 - 1] + totals[count / 2]) as f64 / 2.0
+    } else {
+        totals[count / 2] as f64
+    };
+    // Nearest-rank method, so every percentile is an actual elf's total
+    let percentiles = PERCENTS
+        .iter()
+        .map(|&percent| Percentile {
+            percent,
+            total: totals[(percent * count).div_ceil(100).max(1) - 1],
+        })
+        .collect_vec();
+    let mut histogram = (0..=max / bucket_width)
+        .map(|i| Bucket {
+            from: i * bucket_width,
+            to: (i + 1) * bucket_width,
+            count: 0,
+        })
+        .collect_vec();
+    for total in &totals {
+        histogram[total / bucket_width].count += 1;
+    }
+    let empty_packs = elves
+        .iter()
+        .filter(|elf| elf.items.is_empty())
+        .map(|elf| elf.index)
+        .collect_vec();
+    Some(Statistics {
+        count,
+        mean,
+        median,
+        percentiles,
+        histogram,
+        empty_packs,
+    })
+}
+
+fn print_statistics(stats: &Statistics) {
+    println!("elves  = {}", stats.count);
+    println!("mean   = {:.1}", stats.mean);
+    println!("median = {:.1}", stats.median);
+    for Percentile { percent, total } in &stats.percentiles {
+        println!("p{:<5} = {}", percent, total);
+    }
+    let width = stats.histogram.last().map_or(0, |b| b.to.to_string().len());
+    for Bucket { from, to, count } in &stats.histogram {
+        println!(
+            "{:>w$}..{:<w$} {:>4} {}",
+            from,
+            to,
+            count,
+            "#".repeat(*count),
+            w = width
+        );
+    }
+    if stats.empty_packs.is_empty() {
+        println!("empty packs: none");
+    } else {
+        println!(
+            "empty packs: elves {}",
+            stats.empty_packs.iter().map(|i| i + 1).join(", ")
+        );
+    }
 }
 
 #[cfg(test)]
@@ -62,7 +289,7 @@ mod tests {
 
 10000";
 
-    fn parse_test_data() -> Vec<usize> {
+    fn parse_test_data() -> Vec<Elf> {
         count_calories(INPUT.lines().map(|s| Result::Ok(String::from(s)))).unwrap()
     }
 
@@ -79,4 +306,71 @@ mod tests {
 
         assert_eq!(answer, 45000);
     }
+
+    #[test]
+    fn ranking() {
+        let elves = parse_test_data();
+        let ranking = top_k(&elves, 2);
+
+        assert_eq!(
+            ranking,
+            vec![
+                &Elf {
+                    index: 3,
+                    total: 24000,
+                    items: vec![7000, 8000, 9000]
+                },
+                &Elf {
+                    index: 2,
+                    total: 11000,
+                    items: vec![5000, 6000]
+                },
+            ]
+        );
+        assert_eq!(top_k(&elves, 0), Vec::<&Elf>::new());
+        assert_eq!(top_k(&elves, 10).len(), 5);
+    }
+
+    #[test]
+    fn short_input() {
+        let elves =
+            count_calories("100\n\n50".lines().map(|s| Result::Ok(String::from(s)))).unwrap();
+
+        assert_eq!(top3_calories(&elves), 150);
+        assert_eq!(max_calories(&[]), 0);
+    }
+
+    #[test]
+    fn inventory_statistics() {
+        let stats = statistics(&parse_test_data(), 10000).unwrap();
+
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.mean, 11000.0);
+        assert_eq!(stats.median, 10000.0);
+        assert_eq!(
+            stats.percentiles.iter().map(|p| p.total).collect_vec(),
+            vec![4000, 6000, 10000, 11000, 24000]
+        );
+        assert_eq!(
+            stats.histogram.iter().map(|b| b.count).collect_vec(),
+            vec![2, 2, 1]
+        );
+        assert_eq!(stats.empty_packs, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn empty_packs() {
+        let elves = count_calories(
+            "1000\n\n\n2000"
+                .lines()
+                .map(|s| Result::Ok(String::from(s))),
+        )
+        .unwrap();
+        let stats = statistics(&elves, 1000).unwrap();
+
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.median, 1000.0);
+        assert_eq!(stats.empty_packs, vec![1]);
+        assert_eq!(statistics(&[], 1000), None);
+    }
 }
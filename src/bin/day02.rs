@@ -1,6 +1,9 @@
+use aoc_2022::args;
+use itertools::Itertools;
 use std::{
+    collections::HashSet,
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
 };
 
@@ -10,53 +13,297 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_score(&lines));
-    println!("problem2 = {}", problem2_score(&lines));
+    let game = match args::value::<String>("game")?.as_deref() {
+        None | Some("rps") => Game::rock_paper_scissors(),
+        Some("rpsls") => Game::rock_paper_scissors_lizard_spock(),
+        Some(other) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown game {:?}, expected rps or rpsls", other),
+            ))
+        }
+    };
+
+    if args::subcommand().as_deref() == Some("optimise") {
+        let budget = args::value::<usize>("budget")?.unwrap_or(0);
+        let rounds = match args::value::<String>("strategy")?.as_deref() {
+            None | Some("shape") => game.rounds(&PlayShape, &lines)?,
+            Some("outcome") => game.rounds(&PlayOutcome, &lines)?,
+            Some(other) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("unknown strategy {:?}, expected shape or outcome", other),
+                ))
+            }
+        };
+        print_analysis(&game, &game.analyse(&rounds, budget));
+        return Result::Ok(());
+    }
+
+    println!("problem1 = {}", problem1_score(&game, &lines)?);
+    println!("problem2 = {}", problem2_score(&game, &lines)?);
     Result::Ok(())
 }
 
-fn problem1_score(strategy: &Vec<String>) -> u64 {
-    // X = rock, Y = paper, Z = scissors
-    strategy
-        .into_iter()
-        .map(|line| match line.as_str() {
-            // A = Rock vs
-            "A X" => 1 + 3,
-            "A Y" => 2 + 6,
-            "A Z" => 3 + 0,
-            // B = Paper vs
-            "B X" => 1 + 0,
-            "B Y" => 2 + 3,
-            "B Z" => 3 + 6,
-            // C = Scissors vs
-            "C X" => 1 + 6,
-            "C Y" => 2 + 0,
-            "C Z" => 3 + 3,
-            _ => panic!("Unexpected input"),
-        })
-        .sum()
+fn print_analysis(game: &Game, analysis: &Analysis) {
+    println!("current score = {}", analysis.current);
+    println!("best score    = {}", analysis.best);
+    println!("worst score   = {}", analysis.worst);
+    println!(
+        "edited score  = {} ({} edits)",
+        analysis.current + analysis.edits.iter().map(|e| e.gain).sum::<u64>(),
+        analysis.edits.len()
+    );
+    for edit in &analysis.edits {
+        println!(
+            "  round {}: {} -> {} (+{})",
+            edit.round + 1,
+            game.names[edit.from.0],
+            game.names[edit.to.0],
+            edit.gain
+        );
+    }
+    let rounds: usize = analysis.opponent_counts.iter().sum();
+    println!("opponent moves:");
+    for (shape, count) in game.shapes().zip(&analysis.opponent_counts) {
+        println!(
+            "  {:<8} {:>5} ({:.1}%)",
+            game.names[shape.0],
+            count,
+            100.0 * *count as f64 / rounds.max(1) as f64
+        );
+    }
 }
 
-fn problem2_score(strategy: &Vec<String>) -> u64 {
-    // X = lose, Y = draw, Z = win
-    strategy
-        .into_iter()
-        .map(|line| match line.as_str() {
-            // A = Rock vs
-            "A X" => 3 + 0,
-            "A Y" => 1 + 3,
-            "A Z" => 2 + 6,
-            // B = Paper vs
-            "B X" => 1 + 0,
-            "B Y" => 2 + 3,
-            "B Z" => 3 + 6,
-            // C = Scissors vs
-            "C X" => 2 + 0,
-            "C Y" => 3 + 3,
-            "C Z" => 1 + 6,
-            _ => panic!("Unexpected input"),
-        })
-        .sum()
+fn problem1_score(game: &Game, strategy: &[String]) -> Result<u64> {
+    game.score(&PlayShape, strategy)
+}
+
+fn problem2_score(game: &Game, strategy: &[String]) -> Result<u64> {
+    game.score(&PlayOutcome, strategy)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+struct Shape(usize);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    fn score(&self) -> u64 {
+        match self {
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
+        }
+    }
+}
+
+// Shapes are scored by their position (1, 2, 3, ...) and identified in the guide by a
+// letter for each column
+struct Game {
+    names: Vec<&'static str>,
+    opponent_codes: Vec<char>,
+    player_codes: Vec<char>,
+    beats: HashSet<(Shape, Shape)>,
+}
+
+impl Game {
+    fn new(
+        names: &[&'static str],
+        opponent_codes: &str,
+        player_codes: &str,
+        beats: HashSet<(Shape, Shape)>,
+    ) -> Self {
+        assert_eq!(names.len(), opponent_codes.chars().count());
+        assert_eq!(names.len(), player_codes.chars().count());
+        Game {
+            names: names.to_vec(),
+            opponent_codes: opponent_codes.chars().collect(),
+            player_codes: player_codes.chars().collect(),
+            beats,
+        }
+    }
+
+    // With an odd number of shapes, each one beats the shapes an odd number of places before it
+    fn cyclic(names: &[&'static str], opponent_codes: &str, player_codes: &str) -> Self {
+        let n = names.len();
+        assert!(n % 2 == 1, "cyclic games need an odd number of shapes");
+        let beats = (0..n)
+            .flat_map(|w| (0..n).map(move |l| (w, l)))
+            .filter(|(w, l)| (w + n - l) % n % 2 == 1)
+            .map(|(w, l)| (Shape(w), Shape(l)))
+            .collect();
+        Game::new(names, opponent_codes, player_codes, beats)
+    }
+
+    fn rock_paper_scissors() -> Self {
+        Game::cyclic(&["Rock", "Paper", "Scissors"], "ABC", "XYZ")
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Self {
+        Game::cyclic(
+            &["Rock", "Paper", "Scissors", "Spock", "Lizard"],
+            "ABCDE",
+            "XYZVW",
+        )
+    }
+
+    fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.names.len()).map(Shape)
+    }
+
+    fn outcome(&self, player: Shape, opponent: Shape) -> Outcome {
+        if self.beats.contains(&(player, opponent)) {
+            Outcome::Win
+        } else if self.beats.contains(&(opponent, player)) {
+            Outcome::Lose
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    fn round_score(&self, player: Shape, opponent: Shape) -> u64 {
+        player.0 as u64 + 1 + self.outcome(player, opponent).score()
+    }
+
+    fn parse_round<S: Strategy>(&self, strategy: &S, line: &str) -> Option<(Shape, Shape)> {
+        let (opponent, code) = line.split_once(' ')?;
+        let mut opponent = opponent.chars();
+        let mut code = code.chars();
+        let (opponent, code) = match (opponent.next(), opponent.next(), code.next(), code.next()) {
+            (Some(opponent), None, Some(code), None) => (opponent, code),
+            _ => return None,
+        };
+        let opponent = Shape(self.opponent_codes.iter().position(|&c| c == opponent)?);
+        Some((opponent, strategy.respond(self, opponent, code)?))
+    }
+
+    // Each round as (opponent, player)
+    fn rounds<S: Strategy>(&self, strategy: &S, guide: &[String]) -> Result<Vec<(Shape, Shape)>> {
+        guide
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                self.parse_round(strategy, line).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("line {}: invalid round {:?}", i + 1, line),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn score<S: Strategy>(&self, strategy: &S, guide: &[String]) -> Result<u64> {
+        Result::Ok(
+            self.rounds(strategy, guide)?
+                .into_iter()
+                .map(|(opponent, player)| self.round_score(player, opponent))
+                .sum(),
+        )
+    }
+
+    fn best_response(&self, opponent: Shape) -> Shape {
+        self.shapes()
+            .max_by_key(|&player| self.round_score(player, opponent))
+            .unwrap()
+    }
+
+    fn worst_response(&self, opponent: Shape) -> Shape {
+        self.shapes()
+            .min_by_key(|&player| self.round_score(player, opponent))
+            .unwrap()
+    }
+
+    // Rounds score independently, so the best edits are simply the biggest individual gains
+    fn analyse(&self, rounds: &[(Shape, Shape)], budget: usize) -> Analysis {
+        let score = |f: &dyn Fn(Shape, Shape) -> Shape| -> u64 {
+            rounds
+                .iter()
+                .map(|&(opponent, player)| self.round_score(f(opponent, player), opponent))
+                .sum()
+        };
+        let edits = rounds
+            .iter()
+            .enumerate()
+            .map(|(round, &(opponent, from))| {
+                let to = self.best_response(opponent);
+                let gain = self.round_score(to, opponent) - self.round_score(from, opponent);
+                Edit {
+                    round,
+                    from,
+                    to,
+                    gain,
+                }
+            })
+            .filter(|edit| edit.gain > 0)
+            .sorted_by_key(|edit| (std::cmp::Reverse(edit.gain), edit.round))
+            .take(budget)
+            .collect_vec();
+        let mut opponent_counts = vec![0; self.names.len()];
+        for (opponent, _) in rounds {
+            opponent_counts[opponent.0] += 1;
+        }
+        Analysis {
+            current: score(&|_, player| player),
+            best: score(&|opponent, _| self.best_response(opponent)),
+            worst: score(&|opponent, _| self.worst_response(opponent)),
+            edits,
+            opponent_counts,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Edit {
+    round: usize,
+    from: Shape,
+    to: Shape,
+    gain: u64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    current: u64,
+    best: u64,
+    worst: u64,
+    edits: Vec<Edit>,
+    opponent_counts: Vec<usize>,
+}
+
+// How to interpret the second column of the strategy guide
+trait Strategy {
+    fn respond(&self, game: &Game, opponent: Shape, code: char) -> Option<Shape>;
+}
+
+// X, Y, Z, ... name the shape to play
+struct PlayShape;
+
+impl Strategy for PlayShape {
+    fn respond(&self, game: &Game, _opponent: Shape, code: char) -> Option<Shape> {
+        game.player_codes.iter().position(|&c| c == code).map(Shape)
+    }
+}
+
+// X = lose, Y = draw, Z = win, playing the lowest scoring shape that does so
+struct PlayOutcome;
+
+impl Strategy for PlayOutcome {
+    fn respond(&self, game: &Game, opponent: Shape, code: char) -> Option<Shape> {
+        let outcome = match code {
+            'X' => Outcome::Lose,
+            'Y' => Outcome::Draw,
+            'Z' => Outcome::Win,
+            _ => return None,
+        };
+        game.shapes()
+            .find(|&player| game.outcome(player, opponent) == outcome)
+    }
 }
 
 #[cfg(test)]
@@ -74,15 +321,58 @@ C Z";
 
     #[test]
     fn problem1() {
-        let answer = problem1_score(&load_test_data());
+        let answer = problem1_score(&Game::rock_paper_scissors(), &load_test_data()).unwrap();
 
         assert_eq!(answer, 15);
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_score(&load_test_data());
+        let answer = problem2_score(&Game::rock_paper_scissors(), &load_test_data()).unwrap();
 
         assert_eq!(answer, 12);
     }
+
+    #[test]
+    fn lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let guide = ["D X", "E V", "A W", "B Z"].map(String::from);
+
+        // Rock loses to Spock, Spock loses to Lizard, Lizard loses to Rock, Scissors beats Paper
+        assert_eq!(game.score(&PlayShape, &guide).unwrap(), 1 + 4 + 5 + 9);
+        // Each outcome picks the lowest shape: Paper beats Spock, Lizard draws, Scissors loses to Rock
+        let guide = ["D Z", "E Y", "A X"].map(String::from);
+        assert_eq!(game.score(&PlayOutcome, &guide).unwrap(), 8 + 8 + 3);
+    }
+
+    #[test]
+    fn invalid_rounds() {
+        let game = Game::rock_paper_scissors();
+        for line in ["A", "D X", "A W", "AA X", "A XY", ""] {
+            assert!(game.score(&PlayShape, &[line.to_owned()]).is_err());
+        }
+        assert!(game.score(&PlayOutcome, &["B V".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn analysis() {
+        let game = Game::rock_paper_scissors();
+        let rounds = game.rounds(&PlayShape, &load_test_data()).unwrap();
+        let analysis = game.analyse(&rounds, 1);
+
+        assert_eq!(analysis.current, 15);
+        assert_eq!(analysis.best, 8 + 9 + 7);
+        assert_eq!(analysis.worst, 3 + 1 + 2);
+        assert_eq!(
+            analysis.edits,
+            vec![Edit {
+                round: 1,
+                from: Shape(0),
+                to: Shape(2),
+                gain: 8
+            }]
+        );
+        assert_eq!(analysis.opponent_counts, vec![1, 1, 1]);
+        assert_eq!(game.analyse(&rounds, 5).edits.len(), 2);
+    }
 }
@@ -1,7 +1,9 @@
+use aoc_2022::args;
 use itertools::Itertools;
 use std::{
+    fmt,
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
 };
 
@@ -13,55 +15,203 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    let group_size = args::value::<usize>("group-size")?.unwrap_or(3);
+
+    if args::flag("report") {
+        for (i, (first, second)) in compartments(&lines)?.into_iter().enumerate() {
+            report("rucksack", i, &first.intersection(second));
+            let show = |set: ItemSet| {
+                if set.is_empty() {
+                    "nothing".to_owned()
+                } else {
+                    set.to_string()
+                }
+            };
+            println!(
+                "  only in first: {}; only in second: {}; {} kinds of item",
+                show(first.difference(second)),
+                show(second.difference(first)),
+                first.union(second).len()
+            );
+        }
+        for (i, badges) in group_badges(&lines, group_size)?.iter().enumerate() {
+            report("group", i, badges);
+        }
+    }
+
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines, group_size)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> u32 {
-    input
+fn problem1_solution(input: &[String]) -> Result<u32> {
+    Ok(score(&compartment_duplicates(input)?))
+}
+
+fn problem2_solution(input: &[String], group_size: usize) -> Result<u32> {
+    Ok(score(&group_badges(input, group_size)?))
+}
+
+// Items shared between the two compartments of each rucksack
+fn compartment_duplicates(input: &[String]) -> Result<Vec<ItemSet>> {
+    Ok(compartments(input)?
         .into_iter()
-        .map(|line| {
+        .map(|(first, second)| first.intersection(second))
+        .collect())
+}
+
+// The items in each half of each rucksack
+fn compartments(input: &[String]) -> Result<Vec<(ItemSet, ItemSet)>> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
             let chars = line.as_bytes();
+            if !chars.len().is_multiple_of(2) {
+                return Err(invalid(i, "odd number of items in rucksack"));
+            }
             let half_len = chars.len() >> 1;
 
-            let rucksack1 = to_priority_bitset(&chars[0..half_len]);
-            let rucksack2 = to_priority_bitset(&chars[half_len..]);
-            score_bitset(rucksack1 & rucksack2)
+            let rucksack1 = ItemSet::from_items(&chars[0..half_len]).map_err(|e| invalid(i, e))?;
+            let rucksack2 = ItemSet::from_items(&chars[half_len..]).map_err(|e| invalid(i, e))?;
+            Ok((rucksack1, rucksack2))
         })
-        .sum()
+        .collect()
 }
 
-fn problem2_solution(input: &Vec<String>) -> u32 {
+// Items carried by every elf in each group of `group_size` consecutive rucksacks
+fn group_badges(input: &[String], group_size: usize) -> Result<Vec<ItemSet>> {
+    if group_size == 0 || !input.len().is_multiple_of(group_size) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{} rucksacks cannot be split into groups of {}",
+                input.len(),
+                group_size
+            ),
+        ));
+    }
     input
-        .into_iter()
-        .map(|line| line.as_bytes())
-        .chunks(3)
-        .into_iter()
-        .map(|chunk| {
-            score_bitset(chunk.fold(u64::MAX, |acc, rucksack| acc & to_priority_bitset(rucksack)))
+        .chunks(group_size)
+        .enumerate()
+        .map(|(g, chunk)| {
+            chunk
+                .iter()
+                .enumerate()
+                .try_fold(ItemSet::all(), |acc, (j, line)| {
+                    let items = ItemSet::from_items(line.as_bytes())
+                        .map_err(|e| invalid(g * group_size + j, e))?;
+                    Ok(acc.intersection(items))
+                })
         })
+        .collect()
+}
+
+fn report(what: &str, index: usize, shared: &ItemSet) {
+    if shared.is_empty() {
+        println!("{} {}: nothing shared", what, index + 1);
+    } else if shared.len() == 1 {
+        println!("{} {}: {}", what, index + 1, shared);
+    } else {
+        println!(
+            "{} {}: {} ({} items)",
+            what,
+            index + 1,
+            shared,
+            shared.len()
+        );
+    }
+}
+
+fn score(sets: &[ItemSet]) -> u32 {
+    sets.iter()
+        .flat_map(|set| set.iter())
+        .map(|priority| priority as u32)
         .sum()
 }
 
-fn score_bitset(duplicates: u64) -> u32 {
-    duplicates.trailing_zeros()
+fn invalid(index: usize, msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("line {}: {}", index + 1, msg),
+    )
 }
 
-fn to_priority(item: u8) -> u8 {
+fn to_priority(item: u8) -> Option<u8> {
     match item {
-        b'a'..=b'z' => item - b'a' + 1,
-        b'A'..=b'Z' => item - b'A' + 27,
-        _ => panic!("Unexpected char in rucksack"),
+        b'a'..=b'z' => Some(item - b'a' + 1),
+        b'A'..=b'Z' => Some(item - b'A' + 27),
+        _ => None,
     }
 }
 
-fn to_priority_bitset(items: &[u8]) -> u64 {
-    // println!("item = {}, {:?}", String::from_utf8_lossy(items), items);
-    items
-        .into_iter()
-        .map(|item| to_priority(*item))
-        .fold(0, |acc, p| acc | (1 << p))
+fn to_item(priority: u8) -> char {
+    match priority {
+        1..=26 => (b'a' + priority - 1) as char,
+        _ => (b'A' + priority - 27) as char,
+    }
+}
+
+// Set of items held as a bitset indexed by priority (1 to 52)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn all() -> Self {
+        ItemSet(((1 << 53) - 1) & !1)
+    }
+
+    fn from_items(items: &[u8]) -> std::result::Result<Self, &'static str> {
+        items.iter().try_fold(ItemSet::default(), |acc, &item| {
+            let p = to_priority(item).ok_or("unexpected char in rucksack")?;
+            Ok(ItemSet(acc.0 | (1 << p)))
+        })
+    }
+
+    fn union(self, other: Self) -> Self {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn intersection(self, other: Self) -> Self {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn difference(self, other: Self) -> Self {
+        ItemSet(self.0 & !other.0)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    // Priorities of the items in the set, lowest first
+    fn iter(&self) -> impl Iterator<Item = u8> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let p = bits.trailing_zeros() as u8;
+            bits &= bits - 1;
+            Some(p)
+        })
+    }
+}
+
+impl fmt::Display for ItemSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.iter()
+                .map(|p| format!("{}({})", to_item(p), p))
+                .join(" ")
+        )
+    }
 }
 
 #[cfg(test)]
@@ -83,25 +233,61 @@ CrZsJsPPZsGzwwsLwLmpwMDw";
     #[test]
     fn problem1() {
         assert_eq!(
-            problem1_solution(&vec!("vJrwpWtwJgWrhcsFMMfFFhFp".to_owned())),
+            problem1_solution(&["vJrwpWtwJgWrhcsFMMfFFhFp".to_owned()]).unwrap(),
             16
         );
-        assert_eq!(to_priority(b'B'), 28);
+        assert_eq!(to_priority(b'B'), Some(28));
         assert_eq!(
-            problem1_solution(&vec!(
-                "hngprFFhFDFhrDpzzQDhtnBJJRJZbZvTcvbfRCJfBRcBJl".to_owned()
-            )),
+            problem1_solution(&["hngprFFhFDFhrDpzzQDhtnBJJRJZbZvTcvbfRCJfBRcBJl".to_owned()])
+                .unwrap(),
             28
         );
 
-        let answer = problem1_solution(&load_test_data());
+        let answer = problem1_solution(&load_test_data()).unwrap();
         assert_eq!(answer, 157);
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data());
+        let answer = problem2_solution(&load_test_data(), 3).unwrap();
 
         assert_eq!(answer, 70);
     }
+
+    #[test]
+    fn item_sets() {
+        let a = ItemSet::from_items(b"abcZ").unwrap();
+        let b = ItemSet::from_items(b"bcdZ").unwrap();
+
+        assert_eq!(a.intersection(b).iter().collect_vec(), vec![2, 3, 52]);
+        assert_eq!(a.union(b).len(), 5);
+        assert_eq!(a.difference(b).iter().collect_vec(), vec![1]);
+        assert!(a.difference(a).is_empty());
+        assert_eq!(ItemSet::all().len(), 52);
+        assert_eq!(a.intersection(b).to_string(), "b(2) c(3) Z(52)");
+
+        let (first, second) = compartments(&["abaA".to_owned()]).unwrap()[0];
+        assert_eq!(first.difference(second).to_string(), "b(2)");
+        assert_eq!(second.difference(first).to_string(), "A(27)");
+    }
+
+    #[test]
+    fn group_sizes() {
+        let badges = group_badges(&load_test_data(), 2).unwrap();
+
+        assert_eq!(badges.len(), 3);
+        assert_eq!(badges[0].to_string(), "f(6) r(18) s(19) F(32) M(39)");
+        assert_eq!(
+            group_badges(&load_test_data(), 6).unwrap()[0],
+            ItemSet::default()
+        );
+        assert!(group_badges(&load_test_data(), 4).is_err());
+    }
+
+    #[test]
+    fn invalid_rucksacks() {
+        assert!(problem1_solution(&["abc".to_owned()]).is_err());
+        assert!(problem1_solution(&["ab1c".to_owned()]).is_err());
+        assert!(problem2_solution(&["a b".to_owned()], 1).is_err());
+    }
 }
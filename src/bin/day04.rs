@@ -1,7 +1,11 @@
+use aoc_2022::{
+    args,
+    interval::{Interval, IntervalSet},
+};
 use itertools::Itertools;
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
     str::FromStr,
 };
@@ -14,46 +18,90 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    if args::flag("report") {
+        for (i, elves) in parse_assignments(&lines)?.iter().enumerate() {
+            println!(
+                "line {}: redundant elves [{}], coverage {}",
+                i + 1,
+                redundant_elves(elves).iter().map(|e| e + 1).join(", "),
+                coverage(elves)
+            );
+        }
+    }
+
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> usize {
-    input
-        .into_iter()
-        .filter(|line| {
-            let (elf1, elf2) = parse_line(line);
-            is_second_contained_by(elf1, elf2) || is_second_contained_by(elf2, elf1)
+fn problem1_solution(input: &[String]) -> Result<usize> {
+    Ok(parse_assignments(input)?
+        .iter()
+        .filter(|elves| !redundant_elves(elves).is_empty())
+        .count())
+}
+
+fn problem2_solution(input: &[String]) -> Result<usize> {
+    Ok(parse_assignments(input)?
+        .iter()
+        .filter(|elves| {
+            elves
+                .iter()
+                .tuple_combinations()
+                .any(|(elf1, elf2)| elf1.overlaps(elf2))
         })
-        .count()
+        .count())
 }
 
-fn problem2_solution(input: &Vec<String>) -> usize {
+fn parse_assignments(input: &[String]) -> Result<Vec<Vec<Interval<u32>>>> {
     input
-        .into_iter()
-        .filter(|line| {
-            let (elf1, elf2) = parse_line(line);
-            elf1.1 >= elf2.0 && elf1.0 <= elf2.1
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            parse_line(line)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e)))
         })
-        .count()
+        .collect()
 }
 
-fn parse_line(line: &str) -> ((u32, u32), (u32, u32)) {
+fn parse_line(line: &str) -> std::result::Result<Vec<Interval<u32>>, String> {
     line.split(',')
         .map(|assignment| {
-            assignment
+            let invalid = || format!("invalid assignment {:?}", assignment);
+            let (start, end) = assignment
                 .split('-')
-                .map(|section| u32::from_str(section).unwrap())
-                .collect_tuple::<(u32, u32)>()
-                .unwrap()
+                .map(u32::from_str)
+                .collect_tuple::<(_, _)>()
+                .ok_or_else(invalid)?;
+            match (start, end) {
+                (Ok(start), Ok(end)) if start <= end => Ok(Interval::new(start, end)),
+                (Ok(_), Ok(_)) => Err(format!("reversed assignment {:?}", assignment)),
+                _ => Err(invalid()),
+            }
         })
-        .collect_tuple()
-        .unwrap()
+        .collect()
 }
 
-fn is_second_contained_by(outer: (u32, u32), inner: (u32, u32)) -> bool {
-    outer.0 <= inner.0 && outer.1 >= inner.1
+// Elves whose sections are all covered by another single elf; of identical assignments only the
+// first is kept
+fn redundant_elves(elves: &[Interval<u32>]) -> Vec<usize> {
+    (0..elves.len())
+        .filter(|&i| {
+            elves.iter().enumerate().any(|(j, other)| {
+                j != i && other.contains(&elves[i]) && (other != &elves[i] || j < i)
+            })
+        })
+        .collect_vec()
+}
+
+// Counted in u64, so even an elf assigned every u32 section has a coverage
+fn coverage(elves: &[Interval<u32>]) -> u64 {
+    elves
+        .iter()
+        .map(|elf| Interval::new(elf.start as u64, elf.end as u64))
+        .collect::<IntervalSet<u64>>()
+        .len()
+        .unwrap()
 }
 
 #[cfg(test)]
@@ -74,14 +122,41 @@ mod tests {
 
     #[test]
     fn problem1() {
-        let answer = problem1_solution(&load_test_data());
+        let answer = problem1_solution(&load_test_data()).unwrap();
         assert_eq!(answer, 2);
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data());
+        let answer = problem2_solution(&load_test_data()).unwrap();
 
         assert_eq!(answer, 4);
     }
+
+    #[test]
+    fn multiple_elves() {
+        let elves = parse_line("2-8,3-7,6-6,1-1,3-7").unwrap();
+
+        assert_eq!(redundant_elves(&elves), vec![1, 2, 4]);
+        assert_eq!(coverage(&elves), 8);
+        assert_eq!(
+            redundant_elves(&parse_line("1-3,5-7,2-6").unwrap()),
+            Vec::<usize>::new()
+        );
+        assert_eq!(coverage(&parse_line("1-3,5-7,2-6").unwrap()), 7);
+    }
+
+    #[test]
+    fn invalid_assignments() {
+        let lines = ["2-4,6-8", "5-3,1-2"].map(String::from);
+
+        assert_eq!(
+            problem1_solution(&lines).unwrap_err().to_string(),
+            "line 2: reversed assignment \"5-3\""
+        );
+        assert_eq!(coverage(&parse_line("0-4294967295").unwrap()), 1 << 32);
+        for line in ["1-2,3", "1-2-3", "a-2", "1-2,", ""] {
+            assert!(parse_line(line).is_err(), "{:?}", line);
+        }
+    }
 }
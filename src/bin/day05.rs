@@ -1,8 +1,9 @@
+use aoc_2022::args;
 use itertools::Itertools;
 use std::{
+    fmt,
     fs::File,
-    io::{BufRead, BufReader, Result},
-    ops::IndexMut,
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
     str::FromStr,
 };
@@ -15,8 +16,32 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    if args::flag("draw") {
+        println!("{}\n", parse_input(&lines).0);
+        println!("{}\n", run(&lines, &CrateMover9000)?);
+        println!("{}\n", run(&lines, &CrateMover9001)?);
+    }
+
+    if let Some(k) = args::value::<usize>("after")? {
+        let (state, instructions) = parse_input(&lines);
+        let mut replay = Replay::new(state, instructions, &CrateMover9000);
+        println!("CrateMover 9000 after {} instructions:", k);
+        println!("{}\n", replay.seek(k)?);
+        let (state, instructions) = parse_input(&lines);
+        let mut replay = Replay::new(state, instructions, &CrateMover9001);
+        println!("CrateMover 9001 after {} instructions:", k);
+        println!("{}\n", replay.seek(k)?);
+    }
+
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines)?);
+    if let Some(capacity) = args::value::<usize>("capacity")? {
+        println!(
+            "capacity {} = {}",
+            capacity,
+            run(&lines, &BatchCrane(capacity))?.top_crates()
+        );
+    }
     Ok(())
 }
 
@@ -27,26 +52,126 @@ struct Instruction {
     to: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 struct State {
     stacks: Vec<Vec<char>>,
 }
 
+// How a crane lays down crates taken from the top of a stack
+trait Crane {
+    // Given the crates being moved, bottom to top, returns the order they are placed in
+    fn restack(&self, crates: Vec<char>) -> Vec<char>;
+}
+
+// Moves one crate at a time, reversing their order
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn restack(&self, mut crates: Vec<char>) -> Vec<char> {
+        crates.reverse();
+        crates
+    }
+}
+
+// Moves all the crates at once, preserving their order
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn restack(&self, crates: Vec<char>) -> Vec<char> {
+        crates
+    }
+}
+
+// Moves up to the given number of crates at a time, preserving the order within each lift
+struct BatchCrane(usize);
+
+impl Crane for BatchCrane {
+    fn restack(&self, crates: Vec<char>) -> Vec<char> {
+        crates
+            .rchunks(self.0.max(1))
+            .flatten()
+            .copied()
+            .collect_vec()
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum MoveError {
+    NoSuchStack(usize),
+    NotEnoughCrates {
+        stack: usize,
+        count: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack(stack) => write!(f, "no stack {}", stack),
+            MoveError::NotEnoughCrates {
+                stack,
+                count,
+                available,
+            } => write!(
+                f,
+                "cannot move {} crates from stack {} holding {}",
+                count, stack, available
+            ),
+        }
+    }
+}
+
 impl State {
-    fn run(&mut self, instruction: &Instruction, preserve_order: bool) {
-        if preserve_order {
-            let from = self.stacks.index_mut(instruction.from - 1);
-            let items = from.drain((from.len() - instruction.count)..).collect_vec();
-            self.stacks[instruction.to - 1].extend(items);
+    fn stack_index(&self, stack: usize) -> std::result::Result<usize, MoveError> {
+        if (1..=self.stacks.len()).contains(&stack) {
+            Ok(stack - 1)
         } else {
-            for _ in 0..instruction.count {
-                let c = self.stacks[instruction.from - 1].pop().unwrap();
-                self.stacks[instruction.to - 1].push(c);
-            }
+            Err(MoveError::NoSuchStack(stack))
         }
     }
 
-    fn to_string(&self) -> String {
+    fn run<C: Crane + ?Sized>(
+        &mut self,
+        instruction: &Instruction,
+        crane: &C,
+    ) -> std::result::Result<Move, MoveError> {
+        let from = self.stack_index(instruction.from)?;
+        let to = self.stack_index(instruction.to)?;
+        let available = self.stacks[from].len();
+        if instruction.count > available {
+            return Err(MoveError::NotEnoughCrates {
+                stack: instruction.from,
+                count: instruction.count,
+                available,
+            });
+        }
+        let taken = self.stacks[from]
+            .drain((available - instruction.count)..)
+            .collect_vec();
+        let placed = crane.restack(taken.clone());
+        self.stacks[to].extend(&placed);
+        Ok(Move {
+            from,
+            to,
+            taken,
+            placed,
+        })
+    }
+
+    fn redo(&mut self, m: &Move) {
+        let from = &mut self.stacks[m.from];
+        from.truncate(from.len() - m.taken.len());
+        self.stacks[m.to].extend(&m.placed);
+    }
+
+    fn undo(&mut self, m: &Move) {
+        let to = &mut self.stacks[m.to];
+        to.truncate(to.len() - m.placed.len());
+        self.stacks[m.from].extend(&m.taken);
+    }
+
+    fn top_crates(&self) -> String {
         self.stacks
             .iter()
             .map(|stack| stack.last().unwrap_or(&' '))
@@ -54,6 +179,109 @@ impl State {
     }
 }
 
+// Draws the stacks in the puzzle's own format, padding every row to the full width
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_owned(),
+                })
+                .join(" ");
+            writeln!(f, "{}", row)?;
+        }
+        let footer = (1..=self.stacks.len())
+            .map(|i| format!(" {} ", i))
+            .join(" ");
+        write!(f, "{}", footer)
+    }
+}
+
+// A move as it was actually carried out, so it can be undone without knowing the crane
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Move {
+    from: usize,
+    to: usize,
+    taken: Vec<char>,
+    placed: Vec<char>,
+}
+
+// Steps through a crane program, keeping every move so it can be undone and redone
+struct Replay<'a, C: ?Sized> {
+    state: State,
+    instructions: Vec<Instruction>,
+    crane: &'a C,
+    history: Vec<Move>,
+    position: usize,
+}
+
+impl<'a, C: Crane + ?Sized> Replay<'a, C> {
+    fn new(state: State, instructions: Vec<Instruction>, crane: &'a C) -> Self {
+        Replay {
+            state,
+            instructions,
+            crane,
+            history: Vec::new(),
+            position: 0,
+        }
+    }
+
+    // Applies the next instruction, returning false at the end of the program
+    fn step(&mut self) -> Result<bool> {
+        if let Some(m) = self.history.get(self.position) {
+            self.state.redo(m);
+        } else if let Some(instruction) = self.instructions.get(self.position) {
+            let m = self
+                .state
+                .run(instruction, self.crane)
+                .map_err(|e| instruction_error(self.position, e))?;
+            self.history.push(m);
+        } else {
+            return Ok(false);
+        }
+        self.position += 1;
+        Ok(true)
+    }
+
+    // Reverts the last applied instruction, returning false at the start of the program
+    fn undo(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        self.state.undo(&self.history[self.position]);
+        true
+    }
+
+    // Moves forwards or backwards to the state after `k` instructions
+    fn seek(&mut self, k: usize) -> Result<&State> {
+        if k > self.instructions.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("only {} instructions", self.instructions.len()),
+            ));
+        }
+        while self.position > k {
+            self.undo();
+        }
+        while self.position < k {
+            self.step()?;
+        }
+        Ok(&self.state)
+    }
+}
+
+fn instruction_error(index: usize, e: MoveError) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("instruction {}: {}", index + 1, e),
+    )
+}
+
 fn parse_input(input: &Vec<String>) -> (State, Vec<Instruction>) {
     let mut lines = input.into_iter();
     let stacks: Vec<Vec<char>> =
@@ -86,24 +314,19 @@ fn parse_input(input: &Vec<String>) -> (State, Vec<Instruction>) {
     (initial_state, instructions)
 }
 
-fn run(input: &Vec<String>, preserve_order: bool) -> String {
-    let (mut state, instructions) = parse_input(input);
-    // println!("initial_state = {:?}", state);
-    // println!("instructions = {:?}", instructions);
-    for instruction in instructions {
-        state.run(&instruction, preserve_order);
-        // println!("state = {:?}", state);
-    }
-    // println!("final_state = {:?}", state);
-    state.to_string()
+fn run<C: Crane + ?Sized>(input: &Vec<String>, crane: &C) -> Result<State> {
+    let (state, instructions) = parse_input(input);
+    let mut replay = Replay::new(state, instructions, crane);
+    while replay.step()? {}
+    Ok(replay.state)
 }
 
-fn problem1_solution(input: &Vec<String>) -> String {
-    run(input, false)
+fn problem1_solution(input: &Vec<String>) -> Result<String> {
+    Ok(run(input, &CrateMover9000)?.top_crates())
 }
 
-fn problem2_solution(input: &Vec<String>) -> String {
-    run(input, true)
+fn problem2_solution(input: &Vec<String>) -> Result<String> {
+    Ok(run(input, &CrateMover9001)?.top_crates())
 }
 
 #[cfg(test)]
@@ -127,13 +350,124 @@ move 1 from 1 to 2";
 
     #[test]
     fn problem1() {
-        let answer = problem1_solution(&load_test_data());
+        let answer = problem1_solution(&load_test_data()).unwrap();
         assert_eq!(answer, "CMZ");
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data());
+        let answer = problem2_solution(&load_test_data()).unwrap();
         assert_eq!(answer, "MCD");
     }
+
+    #[test]
+    fn cranes() {
+        let crates = vec!['a', 'b', 'c', 'd', 'e'];
+
+        assert_eq!(
+            CrateMover9000.restack(crates.clone()),
+            ['e', 'd', 'c', 'b', 'a']
+        );
+        assert_eq!(
+            CrateMover9001.restack(crates.clone()),
+            ['a', 'b', 'c', 'd', 'e']
+        );
+        assert_eq!(
+            BatchCrane(2).restack(crates.clone()),
+            ['d', 'e', 'b', 'c', 'a']
+        );
+        assert_eq!(
+            BatchCrane(1).restack(crates.clone()),
+            ['e', 'd', 'c', 'b', 'a']
+        );
+        assert_eq!(BatchCrane(5).restack(crates), ['a', 'b', 'c', 'd', 'e']);
+        assert_eq!(
+            run(&load_test_data(), &BatchCrane(2)).unwrap().top_crates(),
+            "MCZ"
+        );
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let (mut state, _) = parse_input(&load_test_data());
+        let mut apply =
+            |count, from, to| state.run(&Instruction { count, from, to }, &CrateMover9000);
+
+        assert_eq!(apply(1, 4, 1), Err(MoveError::NoSuchStack(4)));
+        assert_eq!(apply(1, 1, 0), Err(MoveError::NoSuchStack(0)));
+        assert_eq!(
+            apply(4, 2, 1),
+            Err(MoveError::NotEnoughCrates {
+                stack: 2,
+                count: 4,
+                available: 3
+            })
+        );
+        assert!(apply(1, 3, 1).is_ok());
+        assert!(apply(1, 3, 1).is_err());
+        assert_eq!(state.top_crates(), "PD ");
+    }
+
+    fn assert_round_trip(lines: &Vec<String>) {
+        let drawing = lines.iter().take_while(|line| !line.is_empty()).join("\n");
+        assert_eq!(parse_input(lines).0.to_string(), drawing);
+    }
+
+    #[test]
+    fn drawing() {
+        assert_round_trip(&load_test_data());
+
+        let state = run(&load_test_data(), &CrateMover9000).unwrap();
+        assert_eq!(
+            state.to_string(),
+            "        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 "
+                .lines()
+                .map(|line| format!("{:<11}", line))
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn replay() {
+        let (initial, instructions) = parse_input(&load_test_data());
+        let mut replay = Replay::new(initial.clone(), instructions, &CrateMover9001);
+
+        assert_eq!(replay.seek(4).unwrap().top_crates(), "MCD");
+        assert_eq!(replay.seek(2).unwrap().top_crates(), " CD");
+        assert!(replay.undo());
+        assert!(replay.undo());
+        assert!(!replay.undo());
+        assert_eq!(replay.state, initial);
+        assert!(replay.step().unwrap());
+        assert_eq!(replay.seek(4).unwrap().top_crates(), "MCD");
+        assert!(!replay.step().unwrap());
+        assert!(replay.seek(5).is_err());
+    }
+
+    #[test]
+    fn undo_move() {
+        let (mut state, instructions) = parse_input(&load_test_data());
+        let before = state.clone();
+        let m = state.run(&instructions[0], &BatchCrane(2)).unwrap();
+        let after = state.clone();
+
+        state.undo(&m);
+        assert_eq!(state, before);
+        state.redo(&m);
+        assert_eq!(state, after);
+    }
+
+    #[test]
+    fn drawing_real_input() {
+        let input_file = File::open(Path::new(INPUT_FILE)).unwrap();
+        let lines = BufReader::new(input_file)
+            .lines()
+            .collect::<Result<Vec<String>>>()
+            .unwrap();
+        assert_round_trip(&lines);
+    }
 }
@@ -1,43 +1,138 @@
-use core::panic;
+use aoc_2022::args;
+use itertools::Itertools;
 use std::{
+    collections::VecDeque,
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{Error, ErrorKind, Read, Result},
     path::Path,
 };
 
 const INPUT_FILE: &str = concat!("./data/", env!("CARGO_BIN_NAME"), ".txt");
 
 fn main() -> Result<()> {
-    let input_file = File::open(Path::new(INPUT_FILE))?;
-    let lines: Vec<String> = BufReader::new(input_file)
-        .lines()
-        .collect::<Result<Vec<String>>>()?;
+    if let Some(window) = args::value::<usize>("window")? {
+        if window == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "--window must be positive",
+            ));
+        }
+        let input_file = File::open(Path::new(INPUT_FILE))?;
+        if args::flag("all") {
+            let positions = Markers::new(input_file, window).collect::<Result<Vec<usize>>>()?;
+            println!("markers = {}", positions.iter().join(", "));
+        } else {
+            println!("marker = {}", show(find_marker(input_file, window)?));
+        }
+        return Ok(());
+    }
 
-    println!("problem1 = {}", problem1_solution(&lines[0]));
-    println!("problem2 = {}", problem2_solution(&lines[0]));
+    let input_file = File::open(Path::new(INPUT_FILE))?;
+    println!("problem1 = {}", show(problem1_solution(input_file)?));
+    let input_file = File::open(Path::new(INPUT_FILE))?;
+    println!("problem2 = {}", show(problem2_solution(input_file)?));
     Ok(())
 }
 
-fn problem1_solution(input: &str) -> usize {
+fn show(marker: Option<usize>) -> String {
+    marker.map_or("not found".to_owned(), |m| m.to_string())
+}
+
+fn problem1_solution<R: Read>(input: R) -> Result<Option<usize>> {
     find_marker(input, 4)
 }
 
-fn problem2_solution(input: &str) -> usize {
+fn problem2_solution<R: Read>(input: R) -> Result<Option<usize>> {
     find_marker(input, 14)
 }
 
-fn find_marker(input: &str, chunk_size: usize) -> usize {
-    let mut chars: Vec<u8> = Vec::new();
-    for i in chunk_size..(input.len()) {
-        chars.clear();
-        chars.extend_from_slice(&input.as_bytes()[(i - chunk_size)..i]);
-        chars.sort();
-        chars.dedup();
-        if chars.len() == chunk_size {
-            return i;
+fn find_marker<R: Read>(input: R, window: usize) -> Result<Option<usize>> {
+    Markers::new(input, window).next().transpose()
+}
+
+// Yields the number of characters read at every point where the last `window` characters are
+// all different. The datastream ends at the end of the input or the first newline.
+struct Markers<R> {
+    reader: R,
+    window: usize,
+    chunk: Box<[u8]>,
+    chunk_start: usize,
+    chunk_end: usize,
+    recent: VecDeque<u8>,
+    counts: [usize; 256],
+    distinct: usize,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> Markers<R> {
+    fn new(reader: R, window: usize) -> Self {
+        assert!(window > 0, "window must not be empty");
+        Markers {
+            reader,
+            window,
+            chunk: vec![0; 4096].into_boxed_slice(),
+            chunk_start: 0,
+            chunk_end: 0,
+            recent: VecDeque::with_capacity(window + 1),
+            counts: [0; 256],
+            distinct: 0,
+            position: 0,
+            done: false,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        while self.chunk_start == self.chunk_end {
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => return Ok(None),
+                Ok(n) => {
+                    self.chunk_start = 0;
+                    self.chunk_end = n;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let byte = self.chunk[self.chunk_start];
+        self.chunk_start += 1;
+        Ok(Some(byte).filter(|&b| b != b'\n'))
+    }
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let byte = match self.next_byte() {
+                Ok(Some(byte)) => byte,
+                Ok(None) => break,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            self.position += 1;
+            self.recent.push_back(byte);
+            self.counts[byte as usize] += 1;
+            if self.counts[byte as usize] == 1 {
+                self.distinct += 1;
+            }
+            if self.recent.len() > self.window {
+                let old = self.recent.pop_front().unwrap();
+                self.counts[old as usize] -= 1;
+                if self.counts[old as usize] == 0 {
+                    self.distinct -= 1;
+                }
+            }
+            if self.distinct == self.window {
+                return Some(Ok(self.position));
+            }
         }
+        self.done = true;
+        None
     }
-    panic!("Not found");
 }
 
 #[cfg(test)]
@@ -53,19 +148,55 @@ mod tests {
 
     #[test]
     fn problem1() {
-        assert_eq!(problem1_solution(INPUT_A), 7);
-        assert_eq!(problem1_solution(INPUT_B), 5);
-        assert_eq!(problem1_solution(INPUT_C), 6);
-        assert_eq!(problem1_solution(INPUT_D), 10);
-        assert_eq!(problem1_solution(INPUT_E), 11);
+        assert_eq!(problem1_solution(INPUT_A.as_bytes()).unwrap(), Some(7));
+        assert_eq!(problem1_solution(INPUT_B.as_bytes()).unwrap(), Some(5));
+        assert_eq!(problem1_solution(INPUT_C.as_bytes()).unwrap(), Some(6));
+        assert_eq!(problem1_solution(INPUT_D.as_bytes()).unwrap(), Some(10));
+        assert_eq!(problem1_solution(INPUT_E.as_bytes()).unwrap(), Some(11));
     }
 
     #[test]
     fn problem2() {
-        assert_eq!(problem2_solution(INPUT_A), 19);
-        assert_eq!(problem2_solution(INPUT_B), 23);
-        assert_eq!(problem2_solution(INPUT_C), 23);
-        assert_eq!(problem2_solution(INPUT_D), 29);
-        assert_eq!(problem2_solution(INPUT_E), 26);
+        assert_eq!(problem2_solution(INPUT_A.as_bytes()).unwrap(), Some(19));
+        assert_eq!(problem2_solution(INPUT_B.as_bytes()).unwrap(), Some(23));
+        assert_eq!(problem2_solution(INPUT_C.as_bytes()).unwrap(), Some(23));
+        assert_eq!(problem2_solution(INPUT_D.as_bytes()).unwrap(), Some(29));
+        assert_eq!(problem2_solution(INPUT_E.as_bytes()).unwrap(), Some(26));
+    }
+
+    // Hands out one byte per read to exercise chunk boundaries
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn markers() {
+        let all = Markers::new("abcabcdd".as_bytes(), 3)
+            .collect::<Result<Vec<usize>>>()
+            .unwrap();
+
+        assert_eq!(all, vec![3, 4, 5, 6, 7]);
+        assert_eq!(
+            Markers::new(Trickle(INPUT_A.as_bytes()), 4)
+                .next()
+                .unwrap()
+                .unwrap(),
+            7
+        );
+        assert_eq!(find_marker("abcd".as_bytes(), 4).unwrap(), Some(4));
+        assert_eq!(find_marker("aaaa".as_bytes(), 2).unwrap(), None);
+        assert_eq!(find_marker("ab\ncd".as_bytes(), 3).unwrap(), None);
+        assert_eq!(find_marker("xyz".as_bytes(), 1).unwrap(), Some(1));
     }
 }
@@ -1,6 +1,11 @@
+use aoc_2022::args;
+use itertools::Itertools;
+use serde::Serialize;
 use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
     str::FromStr,
 };
@@ -13,66 +18,410 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    let max_size = args::value::<usize>("max-size")?.unwrap_or(100000);
+    let disk_size = args::value::<usize>("disk-size")?.unwrap_or(70000000);
+    let required = args::value::<usize>("required")?.unwrap_or(30000000);
+
+    match args::subcommand().as_deref() {
+        None => {
+            println!("problem1 = {}", problem1_solution(&lines, max_size)?);
+            println!(
+                "problem2 = {}",
+                problem2_solution(&lines, disk_size, required)?
+            );
+        }
+        Some("tree") => print!("{}", parse_tree(&lines)?.tree()),
+        Some("du") => {
+            for (path, size) in parse_tree(&lines)?
+                .dir_sizes()
+                .into_iter()
+                .sorted_by_key(|(path, size)| (Reverse(*size), path.clone()))
+            {
+                println!("{}\t{}", size, path);
+            }
+        }
+        Some("find") => {
+            let name = args::value::<String>("name")?;
+            let min_size = args::value::<usize>("min-size")?.unwrap_or(0);
+            for (path, size) in parse_tree(&lines)?.find(name.as_deref(), min_size) {
+                println!("{}\t{}", size, path);
+            }
+        }
+        Some("json") => println!("{}", serde_json::to_string_pretty(&parse_tree(&lines)?)?),
+        Some("plan") => {
+            let protected = args::value::<String>("protect")?
+                .map(|paths| paths.split(',').map(str::to_owned).collect_vec())
+                .unwrap_or_default();
+            let root = parse_tree(&lines)?;
+            match plan_deletion(&root, space_needed(&root, disk_size, required), &protected) {
+                Some(plan) => {
+                    for path in &plan.paths {
+                        println!("{}", path);
+                    }
+                    println!("freed = {}", plan.freed);
+                }
+                None => println!("no plan frees enough space"),
+            }
+        }
+        Some(other) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "unknown command {:?}, expected tree, du, find, json or plan",
+                    other
+                ),
+            ))
+        }
+    }
     Ok(())
 }
 
-struct Dir {
-    total_size: usize,
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
+struct FsNode {
+    files: BTreeMap<String, usize>,
+    children: BTreeMap<String, FsNode>,
 }
 
-impl Dir {
-    const fn new() -> Dir {
-        Dir { total_size: 0 }
+impl FsNode {
+    fn dir_mut(&mut self, path: &[String]) -> &mut FsNode {
+        path.iter().fold(self, |node, name| {
+            node.children.entry(name.clone()).or_default()
+        })
+    }
+
+    fn total_size(&self) -> usize {
+        self.files.values().sum::<usize>()
+            + self
+                .children
+                .values()
+                .map(FsNode::total_size)
+                .sum::<usize>()
+    }
+
+    // Every directory's path and total size, children before their parents
+    fn dir_sizes(&self) -> Vec<(String, usize)> {
+        let mut sizes = Vec::new();
+        self.collect_sizes("", &mut sizes);
+        sizes
+    }
+
+    fn collect_sizes(&self, path: &str, sizes: &mut Vec<(String, usize)>) -> usize {
+        let total = self.files.values().sum::<usize>()
+            + self
+                .children
+                .iter()
+                .map(|(name, child)| child.collect_sizes(&format!("{}/{}", path, name), sizes))
+                .sum::<usize>();
+        sizes.push((if path.is_empty() { "/" } else { path }.to_owned(), total));
+        total
+    }
+
+    // Every directory's path and total size in pre-order, with the index just past its subtree
+    fn preorder(&self) -> Vec<(String, usize, usize)> {
+        let mut dirs = Vec::new();
+        self.collect_preorder("", &mut dirs);
+        dirs
+    }
+
+    fn collect_preorder(&self, path: &str, dirs: &mut Vec<(String, usize, usize)>) -> usize {
+        let index = dirs.len();
+        dirs.push((if path.is_empty() { "/" } else { path }.to_owned(), 0, 0));
+        let total = self.files.values().sum::<usize>()
+            + self
+                .children
+                .iter()
+                .map(|(name, child)| child.collect_preorder(&format!("{}/{}", path, name), dirs))
+                .sum::<usize>();
+        dirs[index].1 = total;
+        dirs[index].2 = dirs.len();
+        total
+    }
+
+    // Paths and sizes of the files matching an optional name glob and a minimum size
+    fn find(&self, name: Option<&str>, min_size: usize) -> Vec<(String, usize)> {
+        let mut found = Vec::new();
+        self.collect_files("", &mut |path, file, size| {
+            if size >= min_size && name.is_none_or(|pattern| glob_match(pattern, file)) {
+                found.push((format!("{}/{}", path, file), size));
+            }
+        });
+        found
+    }
+
+    fn collect_files(&self, path: &str, f: &mut dyn FnMut(&str, &str, usize)) {
+        for (name, size) in &self.files {
+            f(path, name, *size);
+        }
+        for (name, child) in &self.children {
+            child.collect_files(&format!("{}/{}", path, name), f);
+        }
     }
 
-    fn add_file(&mut self, size: usize) {
-        self.total_size += size;
+    // Draws the tree in the style of the puzzle description, with `tree`-like branches
+    fn tree(&self) -> String {
+        let mut out = format!("/ (dir, size={})\n", self.total_size());
+        self.draw(&mut out, "");
+        out
     }
+
+    fn draw(&self, out: &mut String, prefix: &str) {
+        let entries = self
+            .children
+            .iter()
+            .map(|(name, child)| (name, Some(child)))
+            .merge_by(self.files.keys().map(|name| (name, None)), |a, b| {
+                a.0 <= b.0
+            })
+            .collect_vec();
+        for (i, (name, child)) in entries.iter().enumerate() {
+            let last = i + 1 == entries.len();
+            let branch = if last { "└── " } else { "├── " };
+            match child {
+                Some(child) => {
+                    out.push_str(&format!(
+                        "{}{}{} (dir, size={})\n",
+                        prefix,
+                        branch,
+                        name,
+                        child.total_size()
+                    ));
+                    child.draw(
+                        out,
+                        &format!("{}{}", prefix, if last { "    " } else { "│   " }),
+                    );
+                }
+                None => out.push_str(&format!(
+                    "{}{}{} (file, size={})\n",
+                    prefix, branch, name, self.files[*name]
+                )),
+            }
+        }
+    }
+}
+
+// Shell-style wildcards: `*` matches any run of characters and `?` any single character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect_vec();
+    let name = name.chars().collect_vec();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    backtrack = Some((bp, bn + 1));
+                    p = bp + 1;
+                    n = bn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Replays the shell session, so revisiting a directory or listing it again changes nothing
+fn parse_tree(input: &[String]) -> Result<FsNode> {
+    let mut root = FsNode::default();
+    let mut cwd: Vec<String> = Vec::new();
+    for (i, line) in input.iter().enumerate() {
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("line {}: unexpected {:?}", i + 1, line),
+            )
+        };
+        match line.split(' ').collect_vec()[..] {
+            ["$", "cd", "/"] => cwd.clear(),
+            ["$", "cd", ".."] => {
+                cwd.pop();
+            }
+            ["$", "cd", name] => {
+                cwd.push(name.to_owned());
+                root.dir_mut(&cwd);
+            }
+            ["$", "ls"] => {}
+            ["dir", name] => {
+                root.dir_mut(&cwd)
+                    .children
+                    .entry(name.to_owned())
+                    .or_default();
+            }
+            [size, name] => {
+                let size = usize::from_str(size).map_err(|_| invalid())?;
+                root.dir_mut(&cwd).files.insert(name.to_owned(), size);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(root)
+}
+
+// Whether `path` is `dir` or somewhere beneath it
+fn within(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    path == dir
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
 }
 
-fn parse_dirs(input: &Vec<String>) -> Vec<Dir> {
-    let mut finished_dirs: Vec<Dir> = Vec::new();
-    let mut cwd: Vec<Dir> = Vec::new();
-    for line in input.iter() {
-        if line.eq("$ cd ..") {
-            let dir = cwd.pop().unwrap();
-            finished_dirs.push(dir);
-        } else if line.starts_with("$ cd ") {
-            let dir = Dir::new();
-            cwd.push(dir);
-        } else if let Some(size) = line
-            .split(' ')
-            .next()
-            .and_then(|size| usize::from_str(size).ok())
-        {
-            for dir in cwd.iter_mut() {
-                dir.add_file(size);
+#[derive(Debug, PartialEq)]
+struct Plan {
+    paths: Vec<String>,
+    freed: usize,
+}
+
+// Chooses non-nested directories freeing at least `needed` bytes while deleting as little as
+// possible. Nothing inside, containing or equal to a protected path may be deleted.
+//
+// Choosing a set of non-nested directories is a walk through the directories in pre-order
+// that either steps to the next one or deletes the current one and skips past its subtree.
+// The sums reachable by each point only grow along the walk, so for every sum below `needed`
+// it is enough to record the earliest point reaching it to recover the chosen directories.
+fn plan_deletion(root: &FsNode, needed: usize, protected: &[String]) -> Option<Plan> {
+    if needed == 0 {
+        return Some(Plan {
+            paths: Vec::new(),
+            freed: 0,
+        });
+    }
+    let dirs = root.preorder();
+    let deletable = |path: &str| !protected.iter().any(|p| within(p, path) || within(path, p));
+    // Deleting every outermost deletable directory frees the most, so give up early when even
+    // that falls short rather than sizing the bitsets for an impossible target
+    let mut freeable = 0;
+    let mut i = 0;
+    while i < dirs.len() {
+        let (path, size, end) = &dirs[i];
+        if deletable(path) {
+            freeable += size;
+            i = *end;
+        } else {
+            i += 1;
+        }
+    }
+    if freeable < needed {
+        return None;
+    }
+    let words = needed.div_ceil(64);
+    let mask = match needed % 64 {
+        0 => u64::MAX,
+        bits => (1 << bits) - 1,
+    };
+    // Bitsets of the sums reachable at the current point, and those arriving at later points
+    let mut reach = vec![0u64; words];
+    let mut pending: BTreeMap<usize, Vec<u64>> = BTreeMap::new();
+    let mut first = vec![u32::MAX; needed];
+    reach[0] = 1;
+    first[0] = 0;
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (i, (path, size, end)) in dirs.iter().enumerate() {
+        if let Some(arriving) = pending.remove(&i) {
+            for (w, (r, a)) in reach.iter_mut().zip(arriving).enumerate() {
+                let mut new = a & !*r;
+                *r |= a;
+                while new != 0 {
+                    first[w * 64 + new.trailing_zeros() as usize] = i as u32;
+                    new &= new - 1;
+                }
             }
         }
+        if !deletable(path) {
+            continue;
+        }
+        if let Some(s) = next_set(&reach, needed.saturating_sub(*size)) {
+            if best.is_none_or(|(freed, _, _)| s + size < freed) {
+                best = Some((s + size, i, s));
+            }
+        }
+        let target = pending.entry(*end).or_insert_with(|| vec![0; words]);
+        shifted_or(target, &reach, *size);
+        target[words - 1] &= mask;
+    }
+
+    let (freed, mut i, mut s) = best?;
+    let mut paths = vec![dirs[i].0.clone()];
+    while s > 0 {
+        let end = first[s] as usize;
+        i = (0..i)
+            .rev()
+            .find(|&j| {
+                let (path, size, next) = &dirs[j];
+                *next == end && *size <= s && first[s - size] as usize <= j && deletable(path)
+            })
+            .unwrap();
+        paths.push(dirs[i].0.clone());
+        s -= dirs[i].1;
+    }
+    paths.sort();
+    Some(Plan { paths, freed })
+}
+
+// The lowest set bit at or above `from`
+fn next_set(bits: &[u64], from: usize) -> Option<usize> {
+    let w = from / 64;
+    let head = bits.get(w)? & (u64::MAX << (from % 64));
+    if head != 0 {
+        return Some(w * 64 + head.trailing_zeros() as usize);
+    }
+    bits[w + 1..]
+        .iter()
+        .position(|&word| word != 0)
+        .map(|k| (w + 1 + k) * 64 + bits[w + 1 + k].trailing_zeros() as usize)
+}
+
+// dst |= src << shift
+fn shifted_or(dst: &mut [u64], src: &[u64], shift: usize) {
+    let (words, bits) = (shift / 64, shift % 64);
+    for w in words..dst.len() {
+        let carry = match bits {
+            0 => 0,
+            _ if w > words => src[w - words - 1] >> (64 - bits),
+            _ => 0,
+        };
+        dst[w] |= src[w - words] << bits | carry;
     }
-    finished_dirs.extend(cwd.drain(0..));
-    finished_dirs
 }
 
-fn problem1_solution(input: &Vec<String>) -> usize {
-    parse_dirs(input)
+fn space_needed(root: &FsNode, disk_size: usize, required: usize) -> usize {
+    (root.total_size() + required).saturating_sub(disk_size)
+}
+
+fn problem1_solution(input: &[String], max_size: usize) -> Result<usize> {
+    Ok(parse_tree(input)?
+        .dir_sizes()
         .into_iter()
-        .map(|dir| dir.total_size)
-        .filter(|size| *size <= 100000)
-        .sum()
+        .map(|(_, size)| size)
+        .filter(|size| *size <= max_size)
+        .sum())
 }
 
-fn problem2_solution(input: &Vec<String>) -> usize {
-    let dirs = &parse_dirs(input);
-    let used_space = dirs.into_iter().map(|dir| dir.total_size).max().unwrap();
-    let space_needed_to_free = used_space - 40000000;
-    dirs.into_iter()
-        .map(|dir| dir.total_size)
+fn problem2_solution(input: &[String], disk_size: usize, required: usize) -> Result<usize> {
+    let root = parse_tree(input)?;
+    let space_needed_to_free = space_needed(&root, disk_size, required);
+    root.dir_sizes()
+        .into_iter()
+        .map(|(_, size)| size)
         .filter(|size| *size >= space_needed_to_free)
         .min()
-        .unwrap()
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "no directory frees the {} bytes needed",
+                    space_needed_to_free
+                ),
+            )
+        })
 }
 
 #[cfg(test)]
@@ -110,14 +459,202 @@ $ ls
 
     #[test]
     fn problem1() {
-        let answer = problem1_solution(&load_test_data());
+        let answer = problem1_solution(&load_test_data(), 100000).unwrap();
         assert_eq!(answer, 95437);
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data());
+        let answer = problem2_solution(&load_test_data(), 70000000, 30000000).unwrap();
 
         assert_eq!(answer, 24933642);
     }
+
+    #[test]
+    fn tree() {
+        let root = parse_tree(&load_test_data()).unwrap();
+
+        assert_eq!(root.total_size(), 48381165);
+        assert_eq!(root.files.keys().collect_vec(), ["b.txt", "c.dat"]);
+        assert_eq!(root.children.keys().collect_vec(), ["a", "d"]);
+        assert_eq!(root.children["a"].children["e"].files["i"], 584);
+        assert_eq!(
+            root.dir_sizes(),
+            vec![
+                ("/a/e".to_owned(), 584),
+                ("/a".to_owned(), 94853),
+                ("/d".to_owned(), 24933642),
+                ("/".to_owned(), 48381165),
+            ]
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let root = parse_tree(&load_test_data()).unwrap();
+
+        assert_eq!(
+            root.find(Some("*.*"), 0),
+            vec![
+                ("/b.txt".to_owned(), 14848514),
+                ("/c.dat".to_owned(), 8504156),
+                ("/a/h.lst".to_owned(), 62596),
+                ("/d/d.ext".to_owned(), 5626152),
+                ("/d/d.log".to_owned(), 8033020),
+            ]
+        );
+        assert_eq!(root.find(Some("?"), 8000000).len(), 0);
+        assert_eq!(root.find(None, 8000000).len(), 3);
+        assert_eq!(problem1_solution(&load_test_data(), 1000).unwrap(), 584);
+        assert_eq!(
+            problem2_solution(&load_test_data(), 50000000, 10000000).unwrap(),
+            24933642
+        );
+        assert_eq!(
+            problem2_solution(&load_test_data(), 60000000, 10000000).unwrap(),
+            584
+        );
+        assert_eq!(
+            problem2_solution(&load_test_data(), 1000, 30000000)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("d.*", "d.log"));
+        assert!(glob_match("*.l?g", "d.log"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(!glob_match("?", "ab"));
+    }
+
+    #[test]
+    fn drawing() {
+        let root = parse_tree(&load_test_data()).unwrap();
+
+        assert_eq!(
+            root.tree(),
+            "/ (dir, size=48381165)
+├── a (dir, size=94853)
+│   ├── e (dir, size=584)
+│   │   └── i (file, size=584)
+│   ├── f (file, size=29116)
+│   ├── g (file, size=2557)
+│   └── h.lst (file, size=62596)
+├── b.txt (file, size=14848514)
+├── c.dat (file, size=8504156)
+└── d (dir, size=24933642)
+    ├── d.ext (file, size=5626152)
+    ├── d.log (file, size=8033020)
+    ├── j (file, size=4060174)
+    └── k (file, size=7214296)
+"
+        );
+    }
+
+    #[test]
+    fn deletion_plans() {
+        let root = parse_tree(&load_test_data()).unwrap();
+        let needed = space_needed(&root, 70000000, 30000000);
+
+        assert_eq!(
+            plan_deletion(&root, needed, &[]),
+            Some(Plan {
+                paths: vec!["/d".to_owned()],
+                freed: 24933642
+            })
+        );
+        assert_eq!(plan_deletion(&root, needed, &["/d".to_owned()]), None);
+
+        let root = parse_tree(
+            &"$ cd /
+$ ls
+dir x
+dir y
+dir z
+100 r
+$ cd x
+$ ls
+dir w
+310 s
+$ cd w
+$ ls
+250 t
+$ cd /
+$ cd y
+$ ls
+400 u
+$ cd /
+$ cd z
+$ ls
+700 v"
+                .lines()
+                .map(|s| s.to_owned())
+                .collect_vec(),
+        )
+        .unwrap();
+        let plan = |needed, protected: &[&str]| {
+            plan_deletion(
+                &root,
+                needed,
+                &protected.iter().map(|p| p.to_string()).collect_vec(),
+            )
+            .map(|plan| (plan.paths, plan.freed))
+        };
+
+        assert_eq!(
+            plan(900, &[]),
+            Some((vec!["/x/w".into(), "/z".into()], 950))
+        );
+        assert_eq!(
+            plan(900, &["/z/"]),
+            Some((vec!["/x".into(), "/y".into()], 960))
+        );
+        assert_eq!(
+            plan(900, &["/x/w"]),
+            Some((vec!["/y".into(), "/z".into()], 1100))
+        );
+        assert_eq!(
+            plan(1500, &[]),
+            Some((vec!["/x".into(), "/y".into(), "/z".into()], 1660))
+        );
+        assert_eq!(plan(0, &["/"]), Some((vec![], 0)));
+        assert_eq!(plan(1, &["/"]), None);
+        assert_eq!(plan(1760, &[]), Some((vec!["/".into()], 1760)));
+        assert_eq!(plan(1761, &[]), None);
+        assert_eq!(plan(1101, &["/x/w"]), None);
+        assert_eq!(plan(usize::MAX, &[]), None);
+    }
+
+    #[test]
+    fn revisiting() {
+        let mut lines = load_test_data();
+        lines.extend(
+            "$ cd /
+$ ls
+dir a
+14848514 b.txt
+$ cd a
+$ cd e
+$ ls
+584 i
+$ cd /
+$ cd d
+$ ls
+4060174 j"
+                .lines()
+                .map(|s| s.to_owned()),
+        );
+
+        assert_eq!(
+            parse_tree(&lines).unwrap(),
+            parse_tree(&load_test_data()).unwrap()
+        );
+        assert!(parse_tree(&["$ rm -rf /".to_owned()]).is_err());
+        assert!(parse_tree(&["12x3 file".to_owned()]).is_err());
+    }
 }
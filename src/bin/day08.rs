@@ -1,7 +1,8 @@
+use aoc_2022::args;
 use itertools::Itertools;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Result},
+    fs::{self, File},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
 };
 
@@ -13,67 +14,225 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
-    Ok(())
-}
-
-fn problem1_solution(input: &Vec<String>) -> usize {
-    let grid = &load_grid(input);
-    let mut visible_count = 0;
-    for (y, row) in grid.into_iter().enumerate() {
-        for x in 0..row.len() {
-            let tree_height = row[x];
-            let is_visible_left = row[0..x].into_iter().all(|t| t < &tree_height);
-            let is_visible_right = row[(x + 1)..].into_iter().all(|t| t < &tree_height);
-            let is_visible_top = grid[0..y].into_iter().all(|r| r[x] < tree_height);
-            let is_visible_bottom = grid[(y + 1)..].into_iter().all(|r| r[x] < tree_height);
-            if is_visible_left || is_visible_right || is_visible_top || is_visible_bottom {
-                visible_count += 1;
+    match args::subcommand().as_deref() {
+        None => {
+            println!("problem1 = {}", problem1_solution(&lines));
+            println!("problem2 = {}", problem2_solution(&lines));
+        }
+        Some("heatmap") => {
+            let grid = load_grid(&lines);
+            let survey = survey(&grid);
+            match args::value::<String>("image")? {
+                Some(path) if path.ends_with(".pgm") => fs::write(&path, pgm(&survey))?,
+                Some(path) if path.ends_with(".ppm") => fs::write(&path, ppm(&survey))?,
+                Some(path) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("unknown image type {:?}, expected .pgm or .ppm", path),
+                    ))
+                }
+                None => {
+                    print!("{}", render_scores(&grid, &survey));
+                    println!();
+                    print!("{}", render_visibility(&survey));
+                    println!();
+                }
             }
+            let top = args::value::<usize>("top")?.unwrap_or(5);
+            for (rank, (x, y)) in top_spots(&survey, top).into_iter().enumerate() {
+                let [left, right, up, down] = survey.distances[y][x];
+                println!(
+                    "#{} x={} y={} score={} (left {}, right {}, up {}, down {})",
+                    rank + 1,
+                    x,
+                    y,
+                    survey.scores[y][x],
+                    left,
+                    right,
+                    up,
+                    down
+                );
+            }
+        }
+        Some(other) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown command {:?}, expected heatmap", other),
+            ))
         }
     }
-    visible_count
+    Ok(())
+}
+
+fn problem1_solution(input: &[String]) -> usize {
+    survey(&load_grid(input))
+        .visible
+        .iter()
+        .flatten()
+        .filter(|visible| **visible)
+        .count()
+}
+
+fn problem2_solution(input: &[String]) -> usize {
+    survey(&load_grid(input))
+        .scores
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+fn load_grid(input: &[String]) -> Vec<Vec<u8>> {
+    input
+        .iter()
+        .map(|row| row.as_bytes().iter().map(|tree| tree - b'0').collect_vec())
+        .collect_vec()
 }
 
-fn problem2_solution(input: &Vec<String>) -> usize {
-    let grid = &load_grid(input);
-    let mut scenic_score_max = 0;
-    for (y, row) in grid.into_iter().enumerate() {
-        for x in 0..row.len() {
-            let tree_height = row[x];
-            let score_left = scenic_score(tree_height, row[0..x].into_iter().rev().copied());
-            let score_right = scenic_score(tree_height, row[(x + 1)..].into_iter().copied());
-            let score_top = scenic_score(tree_height, grid[0..y].into_iter().map(|r| r[x]).rev());
-            let score_bottom = scenic_score(tree_height, grid[(y + 1)..].into_iter().map(|r| r[x]));
-            let score_total = score_left * score_right * score_top * score_bottom;
-            if score_total > scenic_score_max {
-                scenic_score_max = score_total;
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+const UP: usize = 2;
+const DOWN: usize = 3;
+
+// Per-tree results, indexed [y][x]; viewing distances are ordered left, right, up, down
+#[derive(Debug, PartialEq)]
+struct Survey {
+    visible: Vec<Vec<bool>>,
+    distances: Vec<Vec<[usize; 4]>>,
+    scores: Vec<Vec<usize>>,
+}
+
+// Walks every row and column once in each direction, keeping a stack of the trees not yet
+// blocked by a taller or equal tree. The tree left on top after popping the shorter ones is
+// the first to block the view back along the line, and an empty stack means no tree does.
+fn survey(grid: &[Vec<u8>]) -> Survey {
+    let height = grid.len();
+    let width = grid.first().map_or(0, Vec::len);
+    let mut visible = vec![vec![false; width]; height];
+    let mut distances = vec![vec![[0; 4]; width]; height];
+    let mut walk = |direction: usize, line: &mut dyn Iterator<Item = (usize, usize)>| {
+        let mut stack: Vec<(usize, u8)> = Vec::new();
+        for (i, (x, y)) in line.enumerate() {
+            let tree_height = grid[y][x];
+            while stack.last().is_some_and(|&(_, h)| h < tree_height) {
+                stack.pop();
             }
+            distances[y][x][direction] = stack.last().map_or(i, |&(j, _)| i - j);
+            visible[y][x] |= stack.is_empty();
+            stack.push((i, tree_height));
         }
+    };
+    for y in 0..height {
+        walk(LEFT, &mut (0..width).map(|x| (x, y)));
+        walk(RIGHT, &mut (0..width).rev().map(|x| (x, y)));
+    }
+    for x in 0..width {
+        walk(UP, &mut (0..height).map(|y| (x, y)));
+        walk(DOWN, &mut (0..height).rev().map(|y| (x, y)));
+    }
+    let scores = distances
+        .iter()
+        .map(|row| row.iter().map(|d| d.iter().product()).collect_vec())
+        .collect_vec();
+    Survey {
+        visible,
+        distances,
+        scores,
     }
-    scenic_score_max
 }
 
-fn load_grid(input: &Vec<String>) -> Vec<Vec<u8>> {
-    input
-        .into_iter()
+// The n highest scoring trees as (x, y), reading order breaking ties
+fn top_spots(survey: &Survey, n: usize) -> Vec<(usize, usize)> {
+    survey
+        .scores
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, score)| (x, y, *score)))
+        .sorted_by_key(|&(x, y, score)| (std::cmp::Reverse(score), y, x))
+        .take(n)
+        .map(|(x, y, _)| (x, y))
+        .collect_vec()
+}
+
+// Scores span several orders of magnitude, so brightness follows the log of the score
+fn levels(survey: &Survey) -> Vec<Vec<u8>> {
+    let max = survey.scores.iter().flatten().copied().max().unwrap_or(0);
+    let scale = ((max + 1) as f64).ln().max(f64::MIN_POSITIVE);
+    survey
+        .scores
+        .iter()
         .map(|row| {
-            row.as_bytes()
-                .into_iter()
-                .map(|tree| tree - b'0')
+            row.iter()
+                .map(|&score| (255.0 * ((score + 1) as f64).ln() / scale).round() as u8)
                 .collect_vec()
         })
         .collect_vec()
 }
 
-fn scenic_score<I: Iterator<Item = u8>>(tree_height: u8, view: I) -> usize {
-    let v = &view.collect_vec();
-    let mut count = v.into_iter().take_while(|t| **t < tree_height).count();
-    if count < v.len() {
-        count += 1;
+// Black through red and yellow to white
+fn heat(level: u8) -> [u8; 3] {
+    let t = level as usize * 3;
+    [
+        t.min(255),
+        t.saturating_sub(255).min(255),
+        t.saturating_sub(510),
+    ]
+    .map(|c| c as u8)
+}
+
+// Each tree's height on a background coloured by its score
+fn render_scores(grid: &[Vec<u8>], survey: &Survey) -> String {
+    let mut out = String::new();
+    for (row, levels) in grid.iter().zip(levels(survey)) {
+        for (tree_height, level) in row.iter().zip(levels) {
+            let [r, g, b] = heat(level);
+            let fg = if level > 170 { 30 } else { 97 };
+            out.push_str(&format!(
+                "\x1b[{};48;2;{};{};{}m{}",
+                fg, r, g, b, tree_height
+            ));
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+// Green `#` for trees visible from outside the grid, grey `.` for hidden ones
+fn render_visibility(survey: &Survey) -> String {
+    survey
+        .visible
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&visible| if visible { "\x1b[32m#" } else { "\x1b[90m." })
+                .join("")
+                + "\x1b[0m\n"
+        })
+        .join("")
+}
+
+// Binary greymap of the scores
+fn pgm(survey: &Survey) -> Vec<u8> {
+    let levels = levels(survey);
+    let mut image = format!("P5\n{} {}\n255\n", width(survey), levels.len()).into_bytes();
+    image.extend(levels.into_iter().flatten());
+    image
+}
+
+// Binary pixmap of the scores in heat colours, with hidden trees dimmed
+fn ppm(survey: &Survey) -> Vec<u8> {
+    let mut image = format!("P6\n{} {}\n255\n", width(survey), survey.scores.len()).into_bytes();
+    for (levels, visible) in levels(survey).into_iter().zip(&survey.visible) {
+        for (level, visible) in levels.into_iter().zip(visible) {
+            image.extend(heat(level).map(|c| if *visible { c } else { c / 2 }));
+        }
     }
-    count
+    image
+}
+
+fn width(survey: &Survey) -> usize {
+    survey.scores.first().map_or(0, Vec::len)
 }
 
 #[cfg(test)]
@@ -103,4 +262,92 @@ mod tests {
 
         assert_eq!(answer, 8);
     }
+
+    // The original scans in all four directions from every tree
+    fn scenic_score<I: Iterator<Item = u8>>(tree_height: u8, view: I) -> usize {
+        let v = &view.collect_vec();
+        let mut count = v.iter().take_while(|t| **t < tree_height).count();
+        if count < v.len() {
+            count += 1;
+        }
+        count
+    }
+
+    fn scan(grid: &[Vec<u8>], x: usize, y: usize) -> (bool, usize) {
+        let row = &grid[y];
+        let tree_height = row[x];
+        let visible = row[0..x].iter().all(|t| t < &tree_height)
+            || row[(x + 1)..].iter().all(|t| t < &tree_height)
+            || grid[0..y].iter().all(|r| r[x] < tree_height)
+            || grid[(y + 1)..].iter().all(|r| r[x] < tree_height);
+        let score = scenic_score(tree_height, row[0..x].iter().rev().copied())
+            * scenic_score(tree_height, row[(x + 1)..].iter().copied())
+            * scenic_score(tree_height, grid[0..y].iter().map(|r| r[x]).rev())
+            * scenic_score(tree_height, grid[(y + 1)..].iter().map(|r| r[x]));
+        (visible, score)
+    }
+
+    #[test]
+    fn survey_grids() {
+        let survey = survey(&load_grid(&load_test_data()));
+
+        assert_eq!(survey.distances[3][2], [2, 2, 2, 1]);
+        assert_eq!(survey.scores[3][2], 8);
+        assert_eq!(survey.scores[1][2], 4);
+        assert!(survey.visible[1][1]);
+        assert!(!survey.visible[1][3]);
+        assert_eq!(problem1_solution(&[]), 0);
+        assert_eq!(problem2_solution(&[]), 0);
+    }
+
+    #[test]
+    fn heatmap() {
+        let grid = load_grid(&load_test_data());
+        let survey = survey(&grid);
+
+        assert_eq!(top_spots(&survey, 3), vec![(2, 3), (1, 2), (2, 1)]);
+        assert_eq!(levels(&survey)[3][2], 255);
+        assert_eq!(levels(&survey)[0][0], 0);
+        assert_eq!((heat(0), heat(255)), ([0, 0, 0], [255, 255, 255]));
+        assert!(render_visibility(&survey).starts_with("\x1b[32m#\x1b[32m#"));
+        assert_eq!(render_scores(&grid, &survey).lines().count(), 5);
+
+        let image = pgm(&survey);
+        assert!(image.starts_with(b"P5\n5 5\n255\n"));
+        assert_eq!(image.len(), 11 + 25);
+        let image = ppm(&survey);
+        assert!(image.starts_with(b"P6\n5 5\n255\n"));
+        assert_eq!(image.len(), 11 + 75);
+    }
+
+    #[test]
+    fn random_grids() {
+        // xorshift, so the grids are the same on every run
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = |n: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n) as usize
+        };
+        for _ in 0..200 {
+            let (width, height, max) = (next(12) + 1, next(12) + 1, next(10) as u64 + 1);
+            let grid = (0..height)
+                .map(|_| (0..width).map(|_| next(max) as u8).collect_vec())
+                .collect_vec();
+            let survey = survey(&grid);
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(
+                        (survey.visible[y][x], survey.scores[y][x]),
+                        scan(&grid, x, y),
+                        "{:?} at ({}, {})",
+                        grid,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
 }
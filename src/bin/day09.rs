@@ -1,7 +1,11 @@
+use aoc_2022::args;
+use itertools::Itertools;
+use serde::Serialize;
 use std::{
+    collections::HashSet,
     fs::File,
-    io::{BufRead, BufReader, Result},
-    ops::{AddAssign, Neg},
+    io::{BufRead, BufReader, Error, ErrorKind, Result},
+    ops::{AddAssign, Sub},
     path::Path,
     str::FromStr,
 };
@@ -14,12 +18,99 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    let knots = args::value::<usize>("knots")?;
+    if knots == Some(0) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--knots must be positive",
+        ));
+    }
+    match args::subcommand().as_deref() {
+        None => {}
+        Some("map") => return print_map(&lines, knots.unwrap_or(2)),
+        Some(other) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown command {:?}, expected map", other),
+            ))
+        }
+    }
+    let knots = match knots {
+        Some(knots) => knots,
+        None => {
+            println!("problem1 = {}", problem1_solution(&lines)?);
+            println!("problem2 = {}", problem2_solution(&lines)?);
+            return Ok(());
+        }
+    };
+    let trajectories = trajectories(&parse_motions(&lines)?, knots);
+    match args::value::<String>("export")?.as_deref() {
+        None => {
+            for (knot, positions) in trajectories.iter().enumerate() {
+                println!("knot {} visits {}", knot, distinct(positions));
+            }
+        }
+        Some("csv") => {
+            println!("step,knot,x,y");
+            for (knot, positions) in trajectories.iter().enumerate() {
+                for (step, Coord { x, y }) in positions.iter().enumerate() {
+                    println!("{},{},{},{}", step, knot, x, y);
+                }
+            }
+        }
+        Some("json") => {
+            let knots = trajectories
+                .iter()
+                .enumerate()
+                .map(|(knot, positions)| Trajectory {
+                    knot,
+                    distinct: distinct(positions),
+                    positions,
+                })
+                .collect_vec();
+            println!("{}", serde_json::to_string(&knots)?);
+        }
+        Some(other) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown export format {:?}, expected csv or json", other),
+            ))
+        }
+    }
+    Ok(())
+}
+
+// Draws the cells visited by one knot, the tail by default, preceded in verbose mode by the
+// whole rope after each motion
+fn print_map(lines: &[String], knots: usize) -> Result<()> {
+    let knot = args::value::<usize>("knot")?.unwrap_or(knots - 1);
+    if knot >= knots {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("--knot must be less than {}", knots),
+        ));
+    }
+    let motions = parse_motions(lines)?;
+    let trajectories = trajectories(&motions, knots);
+    let verbose = args::flag("verbose");
+    let bounds = match args::value::<Bounds>("crop")? {
+        Some(bounds) => bounds,
+        None if verbose => Bounds::around(trajectories.iter().flatten()),
+        None => Bounds::around(&trajectories[knot]),
+    };
+    if verbose {
+        let mut step = 0;
+        for (line, motion) in lines.iter().zip(&motions) {
+            step += motion.count;
+            let rope = trajectories.iter().map(|t| t[step]).collect_vec();
+            println!("== {} ==\n\n{}", line, rope_state(&rope, &bounds));
+        }
+    }
+    print!("{}", visited_map(&trajectories[knot], &bounds));
     Ok(())
 }
 
-#[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd, Default)]
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Default, Serialize)]
 struct Coord {
     x: i32,
     y: i32,
@@ -38,61 +129,204 @@ impl Coord {
     }
 }
 
-impl Neg for Coord {
+impl AddAssign<&Coord> for Coord {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl Sub for Coord {
     type Output = Self;
-    fn neg(self) -> Self {
+    fn sub(self, rhs: Self) -> Self {
         Coord {
-            x: -self.x,
-            y: -self.y,
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
         }
     }
 }
 
-impl AddAssign<&Coord> for Coord {
-    fn add_assign(&mut self, rhs: &Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
+#[derive(Debug, PartialEq)]
+struct Motion {
+    direction: Coord,
+    count: usize,
+}
+
+// One motion per line, moving the head in any of the eight directions, e.g. `R 4` or `UL 2`
+fn parse_motions(input: &[String]) -> Result<Vec<Motion>> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let invalid = || {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: invalid motion {:?}", i + 1, line),
+                )
+            };
+            let (direction, count) = line.split_once(' ').ok_or_else(invalid)?;
+            let (x, y) = match direction {
+                "L" => (-1, 0),
+                "R" => (1, 0),
+                "U" => (0, 1),
+                "D" => (0, -1),
+                "UL" | "LU" => (-1, 1),
+                "UR" | "RU" => (1, 1),
+                "DL" | "LD" => (-1, -1),
+                "DR" | "RD" => (1, -1),
+                _ => return Err(invalid()),
+            };
+            Ok(Motion {
+                direction: Coord::new(x, y),
+                count: usize::from_str(count).map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+// Knot 0 is the head; each following knot moves one step towards the knot ahead of it
+// whenever they stop touching
+struct Rope {
+    knots: Vec<Coord>,
+}
+
+impl Rope {
+    fn new(length: usize) -> Self {
+        assert!(length > 0, "a rope needs at least one knot");
+        Rope {
+            knots: vec![Coord::default(); length],
+        }
+    }
+
+    fn step(&mut self, direction: &Coord) {
+        self.knots[0] += direction;
+        for i in 1..self.knots.len() {
+            let upstream_offset = self.knots[i - 1] - self.knots[i];
+            if upstream_offset.x.abs().max(upstream_offset.y.abs()) > 1 {
+                self.knots[i] += &upstream_offset.signum();
+            }
+        }
     }
 }
 
-fn problem1_solution(input: &Vec<String>) -> usize {
-    solve::<2>(input)
-}
-
-fn problem2_solution(input: &Vec<String>) -> usize {
-    solve::<10>(input)
-}
-
-fn solve<const N: usize>(input: &Vec<String>) -> usize
-where
-    [Coord; N]: Default,
-{
-    let mut tail_visits: Vec<Coord> = Vec::new();
-    let mut offsets: [Coord; N] = Default::default();
-    for line in input {
-        let direction = &match line.as_bytes()[0] {
-            b'L' => Coord::new(-1, 0),
-            b'R' => Coord::new(1, 0),
-            b'U' => Coord::new(0, 1),
-            _ => Coord::new(0, -1),
-        };
-        let count = usize::from_str(&line[2..]).unwrap();
-        for _ in 0..count {
-            offsets[0] += direction;
-            for i in 0..(N - 1) {
-                let upstream_offset = &offsets[i];
-                if upstream_offset.x.abs().max(upstream_offset.y.abs()) > 1 {
-                    let signum = upstream_offset.signum();
-                    offsets[i + 1] += &signum;
-                    offsets[i] += &-signum;
-                }
+// Every knot's position at the start and after each step, indexed by knot then step
+fn trajectories(motions: &[Motion], knots: usize) -> Vec<Vec<Coord>> {
+    let mut rope = Rope::new(knots);
+    let mut trajectories = rope.knots.iter().map(|knot| vec![*knot]).collect_vec();
+    for motion in motions {
+        for _ in 0..motion.count {
+            rope.step(&motion.direction);
+            for (trajectory, knot) in trajectories.iter_mut().zip(&rope.knots) {
+                trajectory.push(*knot);
             }
-            tail_visits.push(offsets[N - 1].clone());
         }
     }
-    tail_visits.sort_unstable();
-    tail_visits.dedup();
-    tail_visits.len()
+    trajectories
+}
+
+fn distinct(positions: &[Coord]) -> usize {
+    positions.iter().unique().count()
+}
+
+// Inclusive rectangle of cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    min: Coord,
+    max: Coord,
+}
+
+impl Bounds {
+    // The smallest rectangle holding the start and all the given positions
+    fn around<'a, I: IntoIterator<Item = &'a Coord>>(positions: I) -> Self {
+        positions.into_iter().fold(
+            Bounds {
+                min: Coord::default(),
+                max: Coord::default(),
+            },
+            |bounds, c| Bounds {
+                min: Coord::new(bounds.min.x.min(c.x), bounds.min.y.min(c.y)),
+                max: Coord::new(bounds.max.x.max(c.x), bounds.max.y.max(c.y)),
+            },
+        )
+    }
+
+    // Rows from the top down, as y increases upwards
+    fn draw(&self, cell: impl Fn(Coord) -> char) -> String {
+        (self.min.y..=self.max.y)
+            .rev()
+            .map(|y| {
+                (self.min.x..=self.max.x)
+                    .map(|x| cell(Coord::new(x, y)))
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+}
+
+// Corners given as `x0,y0,x1,y1`
+impl FromStr for Bounds {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let corners: Vec<i32> = s
+            .split(',')
+            .map(|n| i32::from_str(n.trim()).map_err(|_| "invalid coordinate"))
+            .collect::<std::result::Result<_, _>>()?;
+        match corners[..] {
+            [x0, y0, x1, y1] => Ok(Bounds {
+                min: Coord::new(x0.min(x1), y0.min(y1)),
+                max: Coord::new(x0.max(x1), y0.max(y1)),
+            }),
+            _ => Err("expected x0,y0,x1,y1"),
+        }
+    }
+}
+
+// `s` for the start and `#` for every other visited cell
+fn visited_map(positions: &[Coord], bounds: &Bounds) -> String {
+    let visited: HashSet<&Coord> = positions.iter().collect();
+    bounds.draw(|c| {
+        if c == Coord::default() {
+            's'
+        } else if visited.contains(&c) {
+            '#'
+        } else {
+            '.'
+        }
+    })
+}
+
+// Knots labelled as in the puzzle, H and T for a two knot rope and H, 1, 2, ... otherwise,
+// with knots nearer the head drawn on top
+fn rope_state(knots: &[Coord], bounds: &Bounds) -> String {
+    bounds.draw(|c| match knots.iter().position(|k| *k == c) {
+        Some(0) => 'H',
+        Some(_) if knots.len() == 2 => 'T',
+        Some(i) => char::from_digit(i as u32, 36).unwrap_or('*'),
+        None if c == Coord::default() => 's',
+        None => '.',
+    })
+}
+
+#[derive(Serialize)]
+struct Trajectory<'a> {
+    knot: usize,
+    distinct: usize,
+    positions: &'a [Coord],
+}
+
+fn problem1_solution(input: &[String]) -> Result<usize> {
+    solve(input, 2)
+}
+
+fn problem2_solution(input: &[String]) -> Result<usize> {
+    solve(input, 10)
+}
+
+fn solve(input: &[String], knots: usize) -> Result<usize> {
+    let trajectories = trajectories(&parse_motions(input)?, knots);
+    Ok(distinct(&trajectories[knots - 1]))
 }
 
 #[cfg(test)]
@@ -115,7 +349,7 @@ R 2";
 
     #[test]
     fn problem1() {
-        let answer = problem1_solution(&load_test_data(INPUT));
+        let answer = problem1_solution(&load_test_data(INPUT)).unwrap();
         assert_eq!(answer, 13);
     }
 
@@ -130,10 +364,73 @@ U 20";
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data(INPUT));
+        let answer = problem2_solution(&load_test_data(INPUT)).unwrap();
         assert_eq!(answer, 1);
 
-        let answer = problem2_solution(&load_test_data(LARGER_INPUT));
+        let answer = problem2_solution(&load_test_data(LARGER_INPUT)).unwrap();
         assert_eq!(answer, 36);
     }
+
+    #[test]
+    fn runtime_ropes() {
+        let motions = parse_motions(&load_test_data(LARGER_INPUT)).unwrap();
+        let trajectories = trajectories(&motions, 10);
+
+        assert_eq!(trajectories.len(), 10);
+        assert!(trajectories
+            .iter()
+            .all(|t| t.len() == 1 + 5 + 8 + 8 + 3 + 17 + 10 + 25 + 20));
+        assert_eq!(trajectories[9].last(), Some(&Coord::new(-11, 6)));
+        assert_eq!(distinct(&trajectories[9]), 36);
+        assert_eq!(solve(&load_test_data(INPUT), 1).unwrap(), 21);
+        assert_eq!(solve(&load_test_data(INPUT), 3).unwrap(), 7);
+    }
+
+    #[test]
+    fn diagonal_motions() {
+        let motions = parse_motions(&load_test_data("UR 3\nLD 1")).unwrap();
+
+        assert_eq!(motions[1].direction, Coord::new(-1, -1));
+        let trajectories = trajectories(&motions, 3);
+        assert_eq!(
+            trajectories[1],
+            vec![
+                Coord::new(0, 0),
+                Coord::new(0, 0),
+                Coord::new(1, 1),
+                Coord::new(2, 2),
+                Coord::new(2, 2)
+            ]
+        );
+        assert_eq!(trajectories[2].iter().unique().count(), 2);
+        for line in ["X 1", "UD 2", "R", "R -1", ""] {
+            assert!(parse_motions(&[line.to_owned()]).is_err());
+        }
+    }
+
+    #[test]
+    fn maps() {
+        let motions = parse_motions(&load_test_data(INPUT)).unwrap();
+        let rope = trajectories(&motions, 2);
+        let bounds = Bounds::around(rope.iter().flatten());
+
+        assert_eq!(bounds, Bounds::from_str("5,4,0,0").unwrap());
+        assert_eq!(
+            rope_state(&[rope[0][24], rope[1][24]], &bounds),
+            "......\n......\n.TH...\n......\ns.....\n"
+        );
+        assert_eq!(
+            visited_map(&rope[1], &bounds),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+
+        let motions = parse_motions(&load_test_data(LARGER_INPUT)).unwrap();
+        let rope = trajectories(&motions, 10);
+        let map = visited_map(&rope[9], &"-11,-5,14,15".parse().unwrap());
+        assert_eq!(map.lines().count(), 21);
+        assert_eq!(map.lines().nth(10), Some("#.............###........."));
+        assert_eq!(map.lines().nth(15), Some("....#......s.........#...."));
+        assert!(Bounds::from_str("1,2,3").is_err());
+        assert!(Bounds::from_str("1,2,3,x").is_err());
+    }
 }
@@ -1,7 +1,9 @@
+use aoc_2022::args;
 use itertools::Itertools;
 use std::{
+    fmt,
     fs::File,
-    io::{BufRead, BufReader, Result},
+    io::{self, BufRead, BufReader, Error, ErrorKind, Result},
     path::Path,
     str::FromStr,
 };
@@ -14,74 +16,431 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2:");
-    println!("{}", problem2_solution(&lines));
+    let mut crt = Crt::default();
+    if let Some(width) = args::value::<usize>("width")? {
+        crt.width = width;
+    }
+    if let Some(height) = args::value::<usize>("height")? {
+        crt.height = height;
+    }
+    if let Some(sprite_width) = args::value::<usize>("sprite-width")? {
+        crt.sprite_width = sprite_width;
+    }
+    if crt.width == 0 || crt.sprite_width == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--width and --sprite-width must be positive",
+        ));
+    }
+    let mut schedule = Schedule::default();
+    if let Some(first) = args::value::<usize>("first-sample")? {
+        schedule.first = first;
+    }
+    if let Some(interval) = args::value::<usize>("sample-every")? {
+        schedule.interval = interval;
+    }
+    let breakpoints = match args::value::<String>("break")? {
+        Some(cycles) => cycles
+            .split(',')
+            .map(|c| {
+                usize::from_str(c).map_err(|_| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid breakpoint {:?}", c),
+                    )
+                })
+            })
+            .collect::<Result<Vec<usize>>>()?,
+        None => Vec::new(),
+    };
+    if args::flag("trace") || !breakpoints.is_empty() {
+        return debug(
+            &parse_program(&lines)?,
+            &crt,
+            &breakpoints,
+            args::flag("trace"),
+        );
+    }
+
+    println!("problem1 = {}", problem1_solution(&lines, &schedule)?);
+    let screen = problem2_solution(&lines, &crt)?;
+    let show_screen = args::flag("screen");
+    if show_screen {
+        print!("{}", screen);
+    }
+    // Only screens drawn as tall as one of the fonts can hold letters
+    if !matches!(screen.lines().count(), 6 | 10) {
+        if !show_screen {
+            print!("{}", screen);
+        }
+        return Ok(());
+    }
+    match recognise(&screen) {
+        Ok(letters) => println!("problem2 = {}", letters),
+        Err(e) => {
+            if !show_screen {
+                print!("{}", screen);
+            }
+            return Err(e);
+        }
+    }
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> i32 {
-    input
-        .into_iter()
-        .map(|line| {
-            line.split(' ')
-                .skip(1)
-                .next()
-                .map(|v| i32::from_str(v).unwrap())
-        })
-        .fold((0, 0, 1), |(sum, cycle, x), v| {
-            let next = match v {
-                Some(v) => (cycle + 2, x + v),
-                None => (cycle + 1, x),
-            };
-            let old_20 = cycle / 20;
-            let new_20 = next.0 / 20;
-            if (new_20 > old_20) && (new_20 & 1 == 1) {
-                (sum + (new_20 * 20 * x), next.0, next.1)
-            } else {
-                (sum, next.0, next.1)
+fn problem1_solution(input: &[String], schedule: &Schedule) -> Result<i32> {
+    Ok(signal_strength(&parse_program(input)?, schedule))
+}
+
+fn problem2_solution(input: &[String], crt: &Crt) -> Result<String> {
+    Ok(crt.draw(Cpu::new(&parse_program(input)?)))
+}
+
+// Sampled during the first cycle and then every interval, or just once given no interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Schedule {
+    first: usize,
+    interval: usize,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            first: 20,
+            interval: 40,
+        }
+    }
+}
+
+impl Schedule {
+    fn contains(&self, cycle: usize) -> bool {
+        match cycle.checked_sub(self.first) {
+            Some(0) => true,
+            Some(since) => self.interval > 0 && since % self.interval == 0,
+            None => false,
+        }
+    }
+}
+
+fn signal_strength(program: &[Instruction], schedule: &Schedule) -> i32 {
+    Cpu::new(program)
+        .filter(|(cycle, _)| schedule.contains(*cycle))
+        .map(|(cycle, x)| cycle as i32 * x)
+        .sum()
+}
+
+// Draws one pixel per cycle, left to right and top to bottom, lighting it when the sprite
+// centred on X covers it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Crt {
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+        }
+    }
+}
+
+impl Crt {
+    // The column and row drawn during a cycle and whether it is lit, or None once the
+    // screen is full
+    fn pixel(&self, cycle: usize, x: i32) -> Option<(usize, usize, bool)> {
+        let position = cycle - 1;
+        if position >= self.width * self.height {
+            return None;
+        }
+        let (column, row) = (position % self.width, position / self.width);
+        let left = x - (self.sprite_width as i32 - 1) / 2;
+        let lit = (left..left + self.sprite_width as i32).contains(&(column as i32));
+        Some((column, row, lit))
+    }
+
+    // The rows drawn so far, each ending in a newline
+    fn draw<I: IntoIterator<Item = (usize, i32)>>(&self, states: I) -> String {
+        states
+            .into_iter()
+            .map_while(|(cycle, x)| self.pixel(cycle, x))
+            .map(|(_, _, lit)| if lit { '#' } else { '.' })
+            .chunks(self.width)
+            .into_iter()
+            .flat_map(|row| row.chain("\n".chars()))
+            .collect()
+    }
+}
+
+// Runs the program printing each cycle when tracing, and stopping at each breakpoint cycle
+// to show the screen so far until Enter is pressed, or `q` to quit
+fn debug(program: &[Instruction], crt: &Crt, breakpoints: &[usize], trace: bool) -> Result<()> {
+    let mut cpu = Cpu::new(program);
+    let mut states = Vec::new();
+    let stdin = io::stdin();
+    loop {
+        let pc = cpu.pc;
+        let Some((cycle, x)) = cpu.next() else {
+            break;
+        };
+        states.push((cycle, x));
+        if trace {
+            println!(
+                "{}",
+                trace_line(cycle, &program[pc], x, crt.pixel(cycle, x))
+            );
+        }
+        if breakpoints.contains(&cycle) {
+            println!(
+                "breakpoint at cycle {}: X={} executing {} (line {})",
+                cycle,
+                x,
+                program[pc],
+                pc + 1
+            );
+            print!("{}", crt.draw(states.iter().copied()));
+            let mut response = String::new();
+            stdin.read_line(&mut response)?;
+            if response.trim() == "q" {
+                break;
             }
+        }
+    }
+    Ok(())
+}
+
+fn trace_line(
+    cycle: usize,
+    instruction: &Instruction,
+    x: i32,
+    pixel: Option<(usize, usize, bool)>,
+) -> String {
+    let pixel = match pixel {
+        Some((column, row, lit)) => {
+            format!("({},{}) {}", column, row, if lit { '#' } else { '.' })
+        }
+        None => "off screen".to_owned(),
+    };
+    format!(
+        "cycle {:>4}  {:<9}  X={:<4} {}",
+        cycle,
+        instruction.to_string(),
+        x,
+        pixel
+    )
+}
+
+// The letters of a font drawn side by side in cells of a fixed width, as they appear on the CRT
+struct Font {
+    letters: &'static str,
+    cell_width: usize,
+    picture: &'static str,
+}
+
+const SMALL_FONT: Font = Font {
+    letters: "ABCEFGHIJKLOPRSUYZ",
+    cell_width: 5,
+    picture: "\
+.##..###...##..####.####..##..#..#..###...##.#..#.#.....##..###..###...###.#..#.#...#####.
+#..#.#..#.#..#.#....#....#..#.#..#...#.....#.#.#..#....#..#.#..#.#..#.#....#..#.#...#...#.
+#..#.###..#....###..###..#....####...#.....#.##...#....#..#.#..#.#..#.#....#..#..#.#...#..
+####.#..#.#....#....#....#.##.#..#...#.....#.#.#..#....#..#.###..###...##..#..#...#...#...
+#..#.#..#.#..#.#....#....#..#.#..#...#..#..#.#.#..#....#..#.#....#.#.....#.#..#...#..#....
+#..#.###...##..####.#.....###.#..#..###..##..#..#.####..##..#....#..#.###...##....#..####.
+",
+};
+
+const LARGE_FONT: Font = Font {
+    letters: "ABCEFGHJKLNPRXZ",
+    cell_width: 8,
+    picture: "\
+..##....#####....####...######..######...####...#....#.....###..#....#..#.......#....#..#####...#####...#....#..######..
+.#..#...#....#..#....#..#.......#.......#....#..#....#......#...#...#...#.......##...#..#....#..#....#..#....#.......#..
+#....#..#....#..#.......#.......#.......#.......#....#......#...#..#....#.......##...#..#....#..#....#...#..#........#..
+#....#..#....#..#.......#.......#.......#.......#....#......#...#.#.....#.......#.#..#..#....#..#....#...#..#.......#...
+#....#..#####...#.......#####...#####...#.......######......#...##......#.......#.#..#..#####...#####.....##.......#....
+######..#....#..#.......#.......#.......#..###..#....#......#...##......#.......#..#.#..#.......#..#......##......#.....
+#....#..#....#..#.......#.......#.......#....#..#....#......#...#.#.....#.......#..#.#..#.......#...#....#..#....#......
+#....#..#....#..#.......#.......#.......#....#..#....#..#...#...#..#....#.......#...##..#.......#...#....#..#...#.......
+#....#..#....#..#....#..#.......#.......#...##..#....#..#...#...#...#...#.......#...##..#.......#....#..#....#..#.......
+#....#..#####....####...######..#........###.#..#....#...###....#....#..######..#....#..#.......#....#..#....#..######..
+",
+};
+
+// Reads the letters off the screen, choosing the font by the screen's height
+fn recognise(screen: &str) -> Result<String> {
+    let font = match screen.lines().count() {
+        6 => SMALL_FONT,
+        10 => LARGE_FONT,
+        n => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("no font is {} rows high", n),
+            ))
+        }
+    };
+    let known = font
+        .letters
+        .chars()
+        .zip(
+            glyphs(font.picture, font.cell_width)
+                .into_iter()
+                .map(|(_, glyph)| glyph),
+        )
+        .collect_vec();
+    let mut recognised = String::new();
+    let mut unrecognised = Vec::new();
+    for (x, glyph) in glyphs(screen, font.cell_width) {
+        match known.iter().find(|(_, g)| *g == glyph) {
+            Some((c, _)) => recognised.push(*c),
+            None => unrecognised.push(x),
+        }
+    }
+    if unrecognised.is_empty() {
+        Ok(recognised)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "unrecognised glyphs at columns {}",
+                unrecognised.iter().join(", ")
+            ),
+        ))
+    }
+}
+
+// Cuts a picture into cells of a fixed width, giving the first column and rows of each cell
+// that isn't blank, with the blank columns either side of the glyph trimmed
+fn glyphs(picture: &str, cell_width: usize) -> Vec<(usize, Vec<String>)> {
+    let rows = picture
+        .lines()
+        .map(|row| row.chars().collect_vec())
+        .collect_vec();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let lit = |x: usize| rows.iter().any(|row| row.get(x) == Some(&'#'));
+    (0..width)
+        .step_by(cell_width)
+        .filter_map(|start| {
+            let cell = start..(start + cell_width).min(width);
+            let first = cell.clone().find(|&x| lit(x))?;
+            let last = cell.rev().find(|&x| lit(x))?;
+            let glyph = rows
+                .iter()
+                .map(|row| {
+                    (first..=last)
+                        .map(|i| row.get(i).copied().unwrap_or('.'))
+                        .collect::<String>()
+                })
+                .collect_vec();
+            Some((start, glyph))
         })
-        .0
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+}
+
+impl Instruction {
+    fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
+
+    // Takes effect at the end of the instruction's last cycle
+    fn execute(&self, registers: &mut Registers) {
+        match self {
+            Instruction::Noop => {}
+            Instruction::Addx(v) => registers.x += v,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(v) => write!(f, "addx {}", v),
+        }
+    }
 }
 
-fn problem2_solution(input: &Vec<String>) -> String {
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let operand = |v: &str| i32::from_str(v).map_err(|_| format!("invalid operand {:?}", v));
+        match s.split(' ').collect_vec()[..] {
+            ["noop"] => Ok(Instruction::Noop),
+            ["addx", v] => Ok(Instruction::Addx(operand(v)?)),
+            ["noop" | "addx", ..] => Err(format!("wrong number of operands in {:?}", s)),
+            [opcode, ..] => Err(format!("unknown instruction {:?}", opcode)),
+            [] => unreachable!(),
+        }
+    }
+}
+
+fn parse_program(input: &[String]) -> Result<Vec<Instruction>> {
     input
-        .into_iter()
-        .map(|line| {
-            line.split(' ')
-                .skip(1)
-                .next()
-                .map(|v| i32::from_str(v).unwrap())
-        })
-        .scan((0, 1), |(cycle, x), v| {
-            let char0 = pixel_output(*cycle, *x);
-            match v {
-                Some(v) => {
-                    let char1 = pixel_output(*cycle + 1, *x);
-                    *x += v;
-                    *cycle += 2;
-                    Some([char0, char1])
-                }
-                None => {
-                    *cycle += 1;
-                    Some([char0, ' '])
-                }
-            }
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            Instruction::from_str(line)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e)))
         })
-        .flatten()
-        .filter(|c| *c != ' ')
-        .chunks(40)
-        .into_iter()
-        .flat_map(|row| row.chain("\n".chars()))
         .collect()
 }
 
-fn pixel_output(cycle: i32, x: i32) -> char {
-    if i32::abs((cycle % 40) - x) <= 1 {
-        '#'
-    } else {
-        '.'
+#[derive(Debug, Clone, PartialEq)]
+struct Registers {
+    x: i32,
+}
+
+// Runs a program one cycle at a time, yielding the cycle number (from 1) and the value of X
+// during that cycle
+struct Cpu<'a> {
+    program: &'a [Instruction],
+    pc: usize,
+    registers: Registers,
+    cycle: usize,
+    // Cycles left before the current instruction completes, or 0 if none has been fetched
+    remaining: usize,
+}
+
+impl<'a> Cpu<'a> {
+    fn new(program: &'a [Instruction]) -> Self {
+        Cpu {
+            program,
+            pc: 0,
+            registers: Registers { x: 1 },
+            cycle: 0,
+            remaining: 0,
+        }
+    }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = (usize, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let instruction = self.program.get(self.pc)?;
+        if self.remaining == 0 {
+            self.remaining = instruction.cycles();
+        }
+        self.cycle += 1;
+        let state = (self.cycle, self.registers.x);
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            instruction.execute(&mut self.registers);
+            self.pc += 1;
+        }
+        Some(state)
     }
 }
 
@@ -243,13 +602,13 @@ noop";
 
     #[test]
     fn problem1() {
-        let answer = problem1_solution(&load_test_data());
+        let answer = problem1_solution(&load_test_data(), &Schedule::default()).unwrap();
         assert_eq!(answer, 13140);
     }
 
     #[test]
     fn problem2() {
-        let answer = problem2_solution(&load_test_data());
+        let answer = problem2_solution(&load_test_data(), &Crt::default()).unwrap();
 
         let expected = "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
@@ -260,4 +619,142 @@ noop";
 ";
         assert_eq!(&answer, expected);
     }
+
+    #[test]
+    fn cpu() {
+        let program = parse_program(&["noop", "addx 3", "addx -5"].map(String::from)).unwrap();
+        let mut cpu = Cpu::new(&program);
+
+        assert_eq!(
+            cpu.by_ref().collect_vec(),
+            vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]
+        );
+        assert_eq!(cpu.registers.x, -1);
+        assert_eq!(cpu.next(), None);
+        assert_eq!(Cpu::new(&[]).count(), 0);
+    }
+
+    #[test]
+    fn invalid_programs() {
+        assert_eq!(
+            Instruction::from_str("mulx 2"),
+            Err("unknown instruction \"mulx\"".to_owned())
+        );
+        for line in ["addx", "addx x", "noop 1", ""] {
+            assert!(parse_program(&[line.to_owned()]).is_err());
+        }
+    }
+
+    #[test]
+    fn letters() {
+        let answer = "####.###..#..#.###..#..#.####..##..#..#.
+#....#..#.#..#.#..#.#..#....#.#..#.#..#.
+###..###..#..#.#..#.####...#..#....####.
+#....#..#.#..#.###..#..#..#...#....#..#.
+#....#..#.#..#.#.#..#..#.#....#..#.#..#.
+#....###...##..#..#.#..#.####..##..#..#.
+";
+        assert_eq!(recognise(answer).unwrap(), "FBURHZCH");
+
+        for font in [SMALL_FONT, LARGE_FONT] {
+            assert_eq!(recognise(font.picture).unwrap(), font.letters);
+            assert_eq!(
+                glyphs(font.picture, font.cell_width).len(),
+                font.letters.len()
+            );
+        }
+
+        // Y fills its whole cell, so it touches the letter after it
+        let cells = |letters: &str| {
+            (0..6)
+                .map(|row| {
+                    letters
+                        .chars()
+                        .map(|c| {
+                            let i = SMALL_FONT.letters.find(c).unwrap() * 5;
+                            &SMALL_FONT.picture.lines().nth(row).unwrap()[i..i + 5]
+                        })
+                        .join("")
+                })
+                .join("\n")
+        };
+        assert!(cells("YAH").starts_with("#...#.##..#..#"));
+        assert_eq!(recognise(&cells("YAH")).unwrap(), "YAH");
+        assert_eq!(recognise(&cells("HYZY")).unwrap(), "HYZY");
+
+        let mut rows = answer
+            .lines()
+            .map(|row| row.chars().collect_vec())
+            .collect_vec();
+        rows[0][10] = '.';
+        rows[5][25] = '.';
+        let screen = rows.iter().map(|row| row.iter().join("")).join("\n");
+        assert_eq!(
+            recognise(&screen).unwrap_err().to_string(),
+            "unrecognised glyphs at columns 10, 25"
+        );
+        assert!(
+            recognise(&problem2_solution(&load_test_data(), &Crt::default()).unwrap()).is_err()
+        );
+    }
+
+    #[test]
+    fn geometry() {
+        let program = parse_program(&load_test_data()).unwrap();
+        let crt = Crt {
+            width: 8,
+            height: 2,
+            sprite_width: 1,
+        };
+
+        assert_eq!(crt.draw(Cpu::new(&program)), ".#...#..\n........\n");
+        assert_eq!(crt.pixel(3, 2), Some((2, 0, true)));
+        assert_eq!(crt.pixel(10, 1), Some((1, 1, true)));
+        assert_eq!(crt.pixel(17, 1), None);
+        let wide = Crt {
+            sprite_width: 4,
+            ..Crt::default()
+        };
+        assert_eq!(wide.pixel(1, 1), Some((0, 0, true)));
+        assert_eq!(wide.pixel(4, 1), Some((3, 0, true)));
+        assert_eq!(wide.pixel(5, 1), Some((4, 0, false)));
+        assert_eq!(
+            trace_line(3, &program[1], 16, crt.pixel(3, 16)),
+            "cycle    3  addx -11   X=16   (2,0) ."
+        );
+
+        // A ten row screen is read in the large font
+        let large = Crt {
+            width: 120,
+            height: 10,
+            sprite_width: 1,
+        };
+        let states = LARGE_FONT
+            .picture
+            .lines()
+            .flat_map(|row| row.chars().enumerate())
+            .enumerate()
+            .map(|(i, (column, c))| (i + 1, if c == '#' { column as i32 } else { -1 }));
+        let screen = large.draw(states);
+        assert_eq!(screen, LARGE_FONT.picture);
+        assert_eq!(recognise(&screen).unwrap(), LARGE_FONT.letters);
+    }
+
+    #[test]
+    fn schedules() {
+        let program = parse_program(&load_test_data()).unwrap();
+        let schedule = Schedule {
+            first: 20,
+            interval: 0,
+        };
+
+        assert_eq!(signal_strength(&program, &schedule), 420);
+        let schedule = Schedule {
+            first: 60,
+            interval: 80,
+        };
+        assert_eq!(signal_strength(&program, &schedule), 1140 + 2940 + 3960);
+        assert!(Schedule::default().contains(220));
+        assert!(!Schedule::default().contains(0));
+    }
 }
@@ -1,3 +1,4 @@
+use aoc_2022::arith::{self, Checked};
 use itertools::Itertools;
 use num_integer::Integer;
 use std::{
@@ -16,24 +17,26 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> usize {
+fn problem1_solution(input: &Vec<String>) -> Checked<usize> {
     solve(input, 20, true)
 }
 
-fn problem2_solution(input: &Vec<String>) -> usize {
+fn problem2_solution(input: &Vec<String>) -> Checked<usize> {
     solve(input, 10000, false)
 }
 
 type WorryLevel = usize;
 
-fn solve(input: &Vec<String>, rounds: usize, divide_by_three: bool) -> usize {
+fn solve(input: &Vec<String>, rounds: usize, divide_by_three: bool) -> Checked<usize> {
     let mut monkeys = parse(input);
-    let modulo = monkeys.iter().fold(1, |acc, m| acc * m.divisor);
+    let modulo = monkeys
+        .iter()
+        .try_fold(1, |acc, m| arith::mul(&acc, &m.divisor))?;
     for _ in 0..rounds {
         for i in 0..monkeys.len() {
             let Monkey {
@@ -44,13 +47,13 @@ fn solve(input: &Vec<String>, rounds: usize, divide_by_three: bool) -> usize {
                 ..
             } = monkeys[i];
             while let Some(item) = monkeys[i].items.pop_front() {
-                let mut new = operation.apply_to(item);
+                let mut new = operation.apply_to(item)?;
                 if divide_by_three {
                     new /= 3;
                 } else {
                     new %= modulo;
                 }
-                if new.is_multiple_of(&divisor) {
+                if Integer::is_multiple_of(&new, &divisor) {
                     monkeys[monkey_index_true].items.push_back(new);
                 } else {
                     monkeys[monkey_index_false].items.push_back(new);
@@ -60,7 +63,7 @@ fn solve(input: &Vec<String>, rounds: usize, divide_by_three: bool) -> usize {
         }
     }
     monkeys.sort_by(|a, b| b.inspection_count.cmp(&a.inspection_count));
-    monkeys[0].inspection_count * monkeys[1].inspection_count
+    arith::mul(&monkeys[0].inspection_count, &monkeys[1].inspection_count)
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
@@ -71,11 +74,11 @@ enum Operation {
 }
 
 impl Operation {
-    fn apply_to(&self, old: WorryLevel) -> WorryLevel {
+    fn apply_to(&self, old: WorryLevel) -> Checked<WorryLevel> {
         match self {
-            Operation::Add(n) => old + n,
-            Operation::Square => old.pow(2),
-            Operation::Multiply(n) => old * n,
+            Operation::Add(n) => arith::add(&old, n),
+            Operation::Square => arith::mul(&old, &old),
+            Operation::Multiply(n) => arith::mul(&old, n),
         }
     }
 }
@@ -168,12 +171,12 @@ Monkey 3:
     #[test]
     fn problem1() {
         let answer = problem1_solution(&load_test_data());
-        assert_eq!(answer, 10605);
+        assert_eq!(answer, Ok(10605));
     }
 
     #[test]
     fn problem2() {
         let answer = problem2_solution(&load_test_data());
-        assert_eq!(answer, 2713310158);
+        assert_eq!(answer, Ok(2713310158));
     }
 }
@@ -1,6 +1,10 @@
+use aoc_2022::{
+    arith::{self, Checked, Overflow},
+    interval::{Interval, IntervalSet},
+};
 use itertools::Itertools;
 use std::{
-    collections::BTreeMap,
+    collections::BTreeSet,
     fs::File,
     io::{BufRead, BufReader, Result},
     path::Path,
@@ -15,58 +19,77 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines, 2000000));
-    println!("problem2 = {}", problem2_solution(&lines, 4000000));
+    println!("problem1 = {}", problem1_solution(&lines, 2000000)?);
+    println!("problem2 = {}", problem2_solution(&lines, 4000000)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>, y: i32) -> usize {
-    let mut occupied: BTreeMap<i32, bool> = BTreeMap::new();
+fn problem1_solution(input: &[String], y: i32) -> Checked<usize> {
+    let mut covered = IntervalSet::new();
+    let mut beacons = BTreeSet::new();
     for line in input {
         let (sx, sy, bx, by) = parse_sensor(line);
-        let distance = (bx - sx).abs() + (by - sy).abs();
+        let distance = manhattan((sx, sy), (bx, by))?;
         println!("{:?}: {:?}: {}", (sx, sy), (bx, by), distance);
-        let dx = distance - (y - sy).abs();
-        for x in (sx - dx)..=(sx + dx) {
-            let is_beacon = *occupied.get(&x).unwrap_or(&false) || (x == bx && y == by);
-            occupied.insert(x, is_beacon);
+        let dx = arith::sub(&distance, &abs_diff(y, sy)?)?;
+        if dx >= 0 {
+            covered.insert(Interval::new(arith::sub(&sx, &dx)?, arith::add(&sx, &dx)?));
+        }
+        if by == y {
+            beacons.insert(bx);
         }
     }
-    occupied.values().filter(|is_beacon| !**is_beacon).count()
+    let beacons_covered = beacons
+        .into_iter()
+        .filter(|&x| covered.contains_point(x))
+        .count();
+    Ok(covered.len().ok_or(Overflow("add"))? as usize - beacons_covered)
 }
 
-fn problem2_solution(input: &Vec<String>, count: i32) -> i64 {
+fn problem2_solution(input: &[String], count: i32) -> Checked<i64> {
     let sensors = &input
         .iter()
         .map(|line| {
             let (sx, sy, bx, by) = parse_sensor(line);
-            let distance = (bx - sx).abs() + (by - sy).abs();
-            (sx, sy, distance)
+            Ok((sx, sy, manhattan((sx, sy), (bx, by))?))
         })
-        .collect_vec();
+        .collect::<Checked<Vec<_>>>()?;
 
     'row: for y in 0..=count {
         let mut x: i32 = 0;
         'col: while x <= count {
             for (sx, sy, d) in sensors {
-                let dx = d - (y - sy).abs();
-                if (x - sx).abs() <= dx {
-                    if (sx + dx) >= count {
+                let dx = arith::sub(d, &abs_diff(y, *sy)?)?;
+                if abs_diff(x, *sx)? <= dx {
+                    let right = arith::add(sx, &dx)?;
+                    if right >= count {
                         continue 'row;
                     } else {
-                        x = sx + dx + 1;
+                        x = arith::add(&right, &1)?;
                         continue 'col;
                     }
                 }
             }
             println!("{}, {}", x, y);
-            return i64::from(x) * 4000000 + i64::from(y);
+            return Ok(i64::from(x) * 4000000 + i64::from(y));
         }
     }
-    0
+    Ok(0)
+}
+
+fn abs_diff(a: i32, b: i32) -> Checked<i32> {
+    if a >= b {
+        arith::sub(&a, &b)
+    } else {
+        arith::sub(&b, &a)
+    }
 }
 
-fn parse_sensor(line: &String) -> (i32, i32, i32, i32) {
+fn manhattan((ax, ay): (i32, i32), (bx, by): (i32, i32)) -> Checked<i32> {
+    arith::add(&abs_diff(ax, bx)?, &abs_diff(ay, by)?)
+}
+
+fn parse_sensor(line: &str) -> (i32, i32, i32, i32) {
     line.split(&['=', ',', ':'])
         .filter_map(|s| i32::from_str(s).ok())
         .collect_tuple()
@@ -100,12 +123,21 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3";
     #[test]
     fn problem1() {
         let answer = problem1_solution(&load_test_data(), 10);
-        assert_eq!(answer, 26);
+        assert_eq!(answer, Ok(26));
     }
 
     #[test]
     fn problem2() {
         let answer = problem2_solution(&load_test_data(), 20);
-        assert_eq!(answer, 56000011);
+        assert_eq!(answer, Ok(56000011));
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn distance_overflow() {
+        let input =
+            vec!["Sensor at x=-2000000000, y=0: closest beacon is at x=2000000000, y=0".to_owned()];
+        assert_eq!(problem1_solution(&input, 0), Err(Overflow("subtract")));
+        assert_eq!(problem2_solution(&input, 20), Err(Overflow("subtract")));
     }
 }
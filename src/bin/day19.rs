@@ -1,3 +1,4 @@
+use aoc_2022::arith::{self, Checked};
 use itertools::Itertools;
 use std::{
     fs::File,
@@ -15,30 +16,23 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> usize {
-    input
-        .iter()
-        .map(|line| {
-            let (id, score) = score_blueprint(line, 24);
-            id * score
-        })
-        .sum()
+fn problem1_solution(input: &Vec<String>) -> Checked<usize> {
+    input.iter().try_fold(0, |total, line| {
+        let (id, score) = score_blueprint(line, 24)?;
+        arith::add(&total, &arith::mul(&id, &score)?)
+    })
 }
 
-fn problem2_solution(input: &Vec<String>) -> usize {
-    input
-        .iter()
-        .take(3)
-        .map(|line| {
-            let (_, score) = score_blueprint(line, 32);
-            score
-        })
-        .product()
+fn problem2_solution(input: &Vec<String>) -> Checked<usize> {
+    input.iter().take(3).try_fold(1, |product, line| {
+        let (_, score) = score_blueprint(line, 32)?;
+        arith::mul(&product, &score)
+    })
 }
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
@@ -64,10 +58,11 @@ impl State {
         Some(State { robots, wallet })
     }
 
-    fn increase_balance(&mut self, amounts: [u8; 4]) {
+    fn increase_balance(&mut self, amounts: [u8; 4]) -> Checked<()> {
         for i in 0..4 {
-            self.wallet[i] += amounts[i];
+            self.wallet[i] = arith::add(&self.wallet[i], &amounts[i])?;
         }
+        Ok(())
     }
 
     fn score(&self, minutes_remaining: usize) -> usize {
@@ -86,7 +81,7 @@ impl State {
     }
 }
 
-fn score_blueprint(line: &str, num_minutes: usize) -> (usize, usize) {
+fn score_blueprint(line: &str, num_minutes: usize) -> Checked<(usize, usize)> {
     let (id, blueprint) = parse_blueprint(line);
 
     println!("Blueprint {}: {:?}", id, blueprint);
@@ -132,12 +127,12 @@ fn score_blueprint(line: &str, num_minutes: usize) -> (usize, usize) {
         for state in this_round.drain(..) {
             // Options: do nothing or build a robot
             let mut do_nothing = state;
-            do_nothing.increase_balance(state.robots);
+            do_nothing.increase_balance(state.robots)?;
             next_round.push(do_nothing);
             for r in 0..4 {
                 let robot_blueprint = blueprint[r];
                 if let Some(mut new_state) = state.try_build(r, robot_blueprint) {
-                    new_state.increase_balance(state.robots);
+                    new_state.increase_balance(state.robots)?;
                     next_round.push(new_state);
                 }
             }
@@ -151,7 +146,7 @@ fn score_blueprint(line: &str, num_minutes: usize) -> (usize, usize) {
         .max()
         .unwrap_or_default();
     println!("Blueprint {} has best score {}", id, best_score);
-    (id, best_score)
+    Ok((id, best_score))
 }
 
 fn parse_blueprint(line: &str) -> (usize, [[u8; 4]; 4]) {
@@ -187,12 +182,12 @@ Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsid
     #[test]
     fn problem1() {
         let answer = problem1_solution(&load_test_data());
-        assert_eq!(answer, 33);
+        assert_eq!(answer, Ok(33));
     }
 
     #[test]
     fn problem2() {
         let answer = problem2_solution(&load_test_data());
-        assert_eq!(answer, 56 * 62);
+        assert_eq!(answer, Ok(56 * 62));
     }
 }
@@ -1,6 +1,6 @@
+use aoc_2022::arith::{self, Checked};
 use std::{
     collections::HashMap,
-    convert::identity,
     fs::File,
     hash::Hash,
     io::{BufRead, BufReader, Result},
@@ -15,41 +15,46 @@ fn main() -> Result<()> {
         .lines()
         .collect::<Result<Vec<String>>>()?;
 
-    println!("problem1 = {}", problem1_solution(&lines));
-    println!("problem2 = {}", problem2_solution(&lines));
+    println!("problem1 = {}", problem1_solution(&lines)?);
+    println!("problem2 = {}", problem2_solution(&lines)?);
     Ok(())
 }
 
-fn problem1_solution(input: &Vec<String>) -> isize {
+fn problem1_solution(input: &[String]) -> Checked<Value> {
     let monkeys = parse(input);
-    eval(&monkeys, &"root").unwrap()
+    eval(&monkeys, &"root").map(Option::unwrap)
 }
 
-fn problem2_solution(input: &Vec<String>) -> isize {
+fn problem2_solution(input: &[String]) -> Checked<Value> {
     let mut expr = parse(input);
     expr.remove(&"humn");
     if let Some(Op::Add(l, r)) = expr.get(&"root") {
-        branch(&expr, l, r, identity, identity)
+        branch(&expr, l, r, Ok, Ok)
     } else {
         panic!("Unexpected root");
     }
 }
 
+#[cfg(not(feature = "bigint"))]
+type Value = i64;
+#[cfg(feature = "bigint")]
+type Value = num_bigint::BigInt;
+
 #[derive(Debug)]
 enum Op<K> {
-    Yell(isize),
+    Yell(Value),
     Add(K, K),
     Sub(K, K),
     Mul(K, K),
     Div(K, K),
 }
 
-fn parse<'a>(input: &'a Vec<String>) -> HashMap<&'a str, Op<&'a str>> {
+fn parse<'a>(input: &'a [String]) -> HashMap<&'a str, Op<&'a str>> {
     input
         .iter()
         .map(|line| {
             let op: Op<&'a str> = line[6..]
-                .parse::<isize>()
+                .parse::<Value>()
                 .map(Op::Yell)
                 .unwrap_or_else(|_| {
                     let lhs = &line[6..10];
@@ -66,46 +71,53 @@ fn parse<'a>(input: &'a Vec<String>) -> HashMap<&'a str, Op<&'a str>> {
         .collect()
 }
 
-fn eval<K>(expr: &HashMap<K, Op<K>>, root: &K) -> Option<isize>
+// Value is BigInt with the bigint feature, so yelled values must be cloned
+#[allow(clippy::clone_on_copy)]
+fn eval<K>(expr: &HashMap<K, Op<K>>, root: &K) -> Checked<Option<Value>>
 where
     K: Eq + Hash,
 {
-    Some(match expr.get(root)? {
-        Op::Yell(x) => *x,
-        Op::Add(l, r) => eval(expr, l)? + eval(expr, r)?,
-        Op::Sub(l, r) => eval(expr, l)? - eval(expr, r)?,
-        Op::Mul(l, r) => eval(expr, l)? * eval(expr, r)?,
-        Op::Div(l, r) => eval(expr, l)? / eval(expr, r)?,
-    })
+    let (l, r, f): (_, _, fn(&Value, &Value) -> Checked<Value>) = match expr.get(root) {
+        None => return Ok(None),
+        Some(Op::Yell(x)) => return Ok(Some(x.clone())),
+        Some(Op::Add(l, r)) => (l, r, arith::add),
+        Some(Op::Sub(l, r)) => (l, r, arith::sub),
+        Some(Op::Mul(l, r)) => (l, r, arith::mul),
+        Some(Op::Div(l, r)) => (l, r, arith::div),
+    };
+    match (eval(expr, l)?, eval(expr, r)?) {
+        (Some(lv), Some(rv)) => f(&lv, &rv).map(Some),
+        _ => Ok(None),
+    }
 }
 
-fn solve<K>(expr: &HashMap<K, Op<K>>, root_key: &K, root_value: isize) -> isize
+fn solve<K>(expr: &HashMap<K, Op<K>>, root_key: &K, root_value: Value) -> Checked<Value>
 where
     K: Eq + Hash,
 {
     if let Some(op) = expr.get(root_key) {
+        let v = &root_value;
         match op {
             Op::Yell(_) => panic!("Unexpectedly yelling"),
-            Op::Add(l, r) => branch(expr, l, r, |lv| root_value - lv, |rv| root_value - rv),
-            Op::Sub(l, r) => branch(expr, l, r, |lv| lv - root_value, |rv| root_value + rv),
-            Op::Mul(l, r) => branch(expr, l, r, |lv| root_value / lv, |rv| root_value / rv),
-            Op::Div(l, r) => branch(expr, l, r, |lv| lv / root_value, |rv| root_value * rv),
+            Op::Add(l, r) => branch(expr, l, r, |lv| arith::sub(v, &lv), |rv| arith::sub(v, &rv)),
+            Op::Sub(l, r) => branch(expr, l, r, |lv| arith::sub(&lv, v), |rv| arith::add(v, &rv)),
+            Op::Mul(l, r) => branch(expr, l, r, |lv| arith::div(v, &lv), |rv| arith::div(v, &rv)),
+            Op::Div(l, r) => branch(expr, l, r, |lv| arith::div(&lv, v), |rv| arith::mul(v, &rv)),
         }
     } else {
-        root_value
+        Ok(root_value)
     }
 }
 
-fn branch<K: Eq + Hash, FL: FnOnce(isize) -> isize, FR: FnOnce(isize) -> isize>(
-    expr: &HashMap<K, Op<K>>,
-    l: &K,
-    r: &K,
-    fl: FL,
-    fr: FR,
-) -> isize {
-    match (eval(expr, l), eval(expr, r)) {
-        (Some(lv), None) => solve(expr, r, fl(lv)),
-        (None, Some(rv)) => solve(expr, l, fr(rv)),
+fn branch<K, FL, FR>(expr: &HashMap<K, Op<K>>, l: &K, r: &K, fl: FL, fr: FR) -> Checked<Value>
+where
+    K: Eq + Hash,
+    FL: FnOnce(Value) -> Checked<Value>,
+    FR: FnOnce(Value) -> Checked<Value>,
+{
+    match (eval(expr, l)?, eval(expr, r)?) {
+        (Some(lv), None) => solve(expr, r, fl(lv)?),
+        (None, Some(rv)) => solve(expr, l, fr(rv)?),
         _ => panic!("incorrect number of arms"),
     }
 }
@@ -138,12 +150,12 @@ hmdt: 32";
     #[test]
     fn problem1() {
         let answer = problem1_solution(&load_test_data());
-        assert_eq!(answer, 152);
+        assert_eq!(answer, Ok(Value::from(152)));
     }
 
     #[test]
     fn problem2() {
         let answer = problem2_solution(&load_test_data());
-        assert_eq!(answer, 301);
+        assert_eq!(answer, Ok(Value::from(301)));
     }
 }
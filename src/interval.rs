@@ -0,0 +1,216 @@
+use num_traits::PrimInt;
+use std::{cmp, iter};
+
+// Inclusive range of integers, as used for section assignments and sensor coverage
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: PrimInt> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "interval must not be empty");
+        Interval { start, end }
+    }
+
+    // Number of points, or None when that doesn't fit in T, as for the full range of T
+    pub fn len(&self) -> Option<T> {
+        self.end.checked_sub(&self.start)?.checked_add(&T::one())
+    }
+
+    pub fn contains_point(&self, x: T) -> bool {
+        self.start <= x && x <= self.end
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| Interval {
+            start: cmp::max(self.start, other.start),
+            end: cmp::min(self.end, other.end),
+        })
+    }
+}
+
+// Disjoint intervals kept sorted, with overlapping or adjacent intervals merged on insert
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, mut interval: Interval<T>) {
+        let lo = self
+            .intervals
+            .partition_point(|i| i.end.saturating_add(T::one()) < interval.start);
+        let hi = self
+            .intervals
+            .partition_point(|i| i.start <= interval.end.saturating_add(T::one()));
+        if lo < hi {
+            interval.start = cmp::min(interval.start, self.intervals[lo].start);
+            interval.end = cmp::max(interval.end, self.intervals[hi - 1].end);
+        }
+        self.intervals.splice(lo..hi, iter::once(interval));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for interval in &other.intervals {
+            result.insert(*interval);
+        }
+        result
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut i, mut j) = (0, 0);
+        let mut intervals = Vec::new();
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (&self.intervals[i], &other.intervals[j]);
+            intervals.extend(a.intersection(b));
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    pub fn contains_point(&self, x: T) -> bool {
+        let i = self.intervals.partition_point(|i| i.end < x);
+        self.intervals.get(i).is_some_and(|i| i.contains_point(x))
+    }
+
+    pub fn contains(&self, interval: &Interval<T>) -> bool {
+        let i = self.intervals.partition_point(|i| i.end < interval.start);
+        self.intervals.get(i).is_some_and(|i| i.contains(interval))
+    }
+
+    // Total number of points covered, or None when that doesn't fit in T
+    pub fn len(&self) -> Option<T> {
+        self.intervals
+            .iter()
+            .try_fold(T::zero(), |acc, interval| acc.checked_add(&interval.len()?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+}
+
+impl<T: PrimInt> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet::new()
+    }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn set(intervals: &[(i32, i32)]) -> IntervalSet<i32> {
+        intervals
+            .iter()
+            .map(|&(s, e)| Interval::new(s, e))
+            .collect()
+    }
+
+    #[test]
+    fn intervals() {
+        let a = Interval::new(2, 8);
+        let b = Interval::new(3, 7);
+
+        assert_eq!(a.len(), Some(7));
+        assert!(a.contains(&b));
+        assert!(!b.contains(&a));
+        assert!(a.overlaps(&Interval::new(8, 9)));
+        assert!(!a.overlaps(&Interval::new(9, 9)));
+        assert_eq!(
+            a.intersection(&Interval::new(6, 12)),
+            Some(Interval::new(6, 8))
+        );
+        assert_eq!(a.intersection(&Interval::new(9, 12)), None);
+    }
+
+    #[test]
+    fn merging() {
+        let s = set(&[(5, 7), (1, 2), (10, 12), (3, 4), (6, 11)]);
+
+        assert_eq!(
+            s.iter().copied().collect::<Vec<_>>(),
+            [Interval::new(1, 12)]
+        );
+        assert_eq!(s.len(), Some(12));
+
+        let s = set(&[(1, 2), (5, 6), (9, 9)]);
+        assert_eq!(s.iter().count(), 3);
+        assert_eq!(s.len(), Some(5));
+        assert!(s.contains_point(5));
+        assert!(!s.contains_point(7));
+        assert!(s.contains(&Interval::new(5, 6)));
+        assert!(!s.contains(&Interval::new(2, 5)));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[(1, 5), (10, 15)]);
+        let b = set(&[(4, 11), (14, 20)]);
+
+        assert_eq!(a.union(&b), set(&[(1, 20)]));
+        assert_eq!(a.intersection(&b), set(&[(4, 5), (10, 11), (14, 15)]));
+        assert!(a.intersection(&set(&[(6, 9)])).is_empty());
+    }
+
+    fn set_u8(x: u8) -> IntervalSet<u8> {
+        [Interval::new(x, x)].into_iter().collect()
+    }
+
+    #[test]
+    fn extremes() {
+        let s: IntervalSet<u32> = [Interval::new(0, 3), Interval::new(4, u32::MAX)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(s.iter().count(), 1);
+        assert!(s.contains_point(u32::MAX));
+        assert_eq!(s.len(), None);
+        assert_eq!(Interval::new(0, u32::MAX).len(), None);
+        assert_eq!(Interval::new(1, u32::MAX).len(), Some(u32::MAX));
+        assert_eq!(Interval::new(i32::MIN, i32::MAX).len(), None);
+        assert_eq!(Interval::new(i32::MIN + 1, -1).len(), Some(i32::MAX));
+
+        let s: IntervalSet<u8> = [Interval::new(0, 127), Interval::new(129, 255)]
+            .into_iter()
+            .collect();
+        assert_eq!(s.len(), Some(255));
+        assert_eq!(s.union(&set_u8(128)).len(), None);
+    }
+}